@@ -0,0 +1,116 @@
+/*
+ * Drives the C API end to end. Build the library first:
+ *
+ *   cargo build --release
+ *   cc -Iinclude c/test_ffi.c -Ltarget/release -lyonmoku -o target/test_ffi
+ *   LD_LIBRARY_PATH=target/release ./target/test_ffi
+ */
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "yonmoku.h"
+
+static void test_board(void) {
+    YonmokuBoard *board = yonmoku_board_new();
+    assert(board != NULL);
+    assert(yonmoku_board_side_to_move(board) == 1);
+
+    uint32_t moves[YONMOKU_COLUMNS];
+    assert(yonmoku_board_legal_moves(board, moves, YONMOKU_COLUMNS) == 16);
+    assert(moves[0] == 0 && moves[15] == 15);
+
+    /* Black a1 b1 c1, White a4 b4: White must block d1 (column 3). */
+    uint32_t record[] = {0, 12, 1, 13, 2};
+    for (size_t n = 0; n < sizeof(record) / sizeof(record[0]); n++) {
+        assert(yonmoku_board_play(board, record[n]) == YONMOKU_STATUS_OK);
+    }
+    assert(yonmoku_board_side_to_move(board) == 2);
+    assert(yonmoku_board_check_column(board) == 3);
+    assert(yonmoku_board_win_column(board) == -1);
+    assert(yonmoku_board_outcome(board) == YONMOKU_OUTCOME_ONGOING);
+    assert(yonmoku_board_play(board, 16) == YONMOKU_STATUS_OUT_OF_RANGE);
+
+    /* The position string round-trips, and a short buffer is truncated. */
+    size_t len = yonmoku_board_position(board, NULL, 0);
+    char position[128];
+    assert(len < sizeof(position));
+    assert(yonmoku_board_position(board, position, sizeof(position)) == len);
+    assert(strlen(position) == len);
+    char short_buf[4];
+    yonmoku_board_position(board, short_buf, sizeof(short_buf));
+    assert(strlen(short_buf) == 3);
+
+    YonmokuBoard *copy = yonmoku_board_from_position(position);
+    assert(copy != NULL);
+    assert(yonmoku_board_from_position("not a position") == NULL);
+
+    /* If White ignores the threat, Black wins on d1. */
+    assert(yonmoku_board_play(copy, 14) == YONMOKU_STATUS_OK);
+    assert(yonmoku_board_win_column(copy) == 3);
+    assert(yonmoku_board_play(copy, 3) == YONMOKU_STATUS_OK);
+    assert(yonmoku_board_outcome(copy) == YONMOKU_OUTCOME_BLACK_WIN);
+    assert(yonmoku_board_play(copy, 4) == YONMOKU_STATUS_GAME_OVER);
+    assert(yonmoku_board_legal_moves(copy, NULL, 0) == 0);
+
+    /* A full column is reported as such. */
+    YonmokuBoard *clone = yonmoku_board_clone(board);
+    for (int n = 0; n < 4; n++) {
+        assert(yonmoku_board_play(clone, 5) == YONMOKU_STATUS_OK);
+    }
+    assert(yonmoku_board_play(clone, 5) == YONMOKU_STATUS_COLUMN_FULL);
+
+    yonmoku_board_free(clone);
+    yonmoku_board_free(copy);
+    yonmoku_board_free(board);
+    yonmoku_board_free(NULL);
+}
+
+static void test_search(void) {
+    YonmokuBoard *board = yonmoku_board_new();
+    YonmokuLimits limits = {
+        .playouts = 200,
+        .time_ms = 0,
+        .seed = 42,
+        .tree = YONMOKU_TREE_PUCT_SIMPLE,
+    };
+    YonmokuSearchResult result;
+    assert(yonmoku_search(board, &limits, &result) == YONMOKU_STATUS_OK);
+    assert(result.seed == 42);
+    assert(!result.forced);
+    assert(result.n_moves == 16);
+    assert(result.best == (int32_t)result.moves[0].column);
+    for (uint32_t n = 0; n < result.n_moves; n++) {
+        YonmokuMoveStats *m = &result.moves[n];
+        assert(m->visits >= 200);
+        assert(m->win + m->draw + m->lose == m->visits);
+        assert(m->prior >= 0);
+    }
+
+    /* The same seed gives the same search. */
+    YonmokuSearchResult again;
+    assert(yonmoku_search(board, &limits, &again) == YONMOKU_STATUS_OK);
+    for (uint32_t n = 0; n < result.n_moves; n++) {
+        assert(result.moves[n].column == again.moves[n].column);
+        assert(result.moves[n].visits == again.moves[n].visits);
+        assert(result.moves[n].win == again.moves[n].win);
+    }
+
+    limits.tree = YONMOKU_TREE_UCB1;
+    assert(yonmoku_search(board, &limits, &result) == YONMOKU_STATUS_OK);
+    assert(result.moves[0].prior == -1);
+
+    limits.tree = 99;
+    assert(yonmoku_search(board, &limits, &result) == YONMOKU_STATUS_INVALID_ARGUMENT);
+    assert(yonmoku_search(NULL, &limits, &result) == YONMOKU_STATUS_INVALID_ARGUMENT);
+
+    yonmoku_board_free(board);
+}
+
+int main(void) {
+    printf("yonmoku %s\n", yonmoku_version());
+    test_board();
+    test_search();
+    printf("ok\n");
+    return 0;
+}
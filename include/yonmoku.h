@@ -0,0 +1,250 @@
+#ifndef YONMOKU_H
+#define YONMOKU_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * 盤の列の数
+ */
+#define YONMOKU_COLUMNS 16
+
+/**
+ * PUCTとsimple policy。既定の木。
+ */
+#define YONMOKU_TREE_PUCT_SIMPLE 0
+
+/**
+ * PUCTとcount policy
+ */
+#define YONMOKU_TREE_PUCT_COUNT 1
+
+/**
+ * UCB1
+ */
+#define YONMOKU_TREE_UCB1 2
+
+/**
+ * 関数の結果
+ */
+typedef enum YonmokuStatus {
+  YONMOKU_STATUS_OK = 0,
+  /**
+   * ポインタがNULLか、引数が正しくない
+   */
+  YONMOKU_STATUS_INVALID_ARGUMENT = 1,
+  /**
+   * 列の番号が範囲外
+   */
+  YONMOKU_STATUS_OUT_OF_RANGE = 2,
+  /**
+   * 列が埋まっている
+   */
+  YONMOKU_STATUS_COLUMN_FULL = 3,
+  /**
+   * 終局している
+   */
+  YONMOKU_STATUS_GAME_OVER = 4,
+} YonmokuStatus;
+
+/**
+ * 局面の状態
+ */
+typedef enum YonmokuOutcome {
+  YONMOKU_OUTCOME_ONGOING = 0,
+  YONMOKU_OUTCOME_BLACK_WIN = 1,
+  YONMOKU_OUTCOME_WHITE_WIN = 2,
+  YONMOKU_OUTCOME_DRAW = 3,
+} YonmokuOutcome;
+
+/**
+ * 読み切りの結果。手番側から見る。
+ */
+typedef enum YonmokuProof {
+  YONMOKU_PROOF_UNKNOWN = 0,
+  YONMOKU_PROOF_WIN = 1,
+  YONMOKU_PROOF_LOSE = 2,
+  YONMOKU_PROOF_DRAW = 3,
+} YonmokuProof;
+
+/**
+ * 盤。中身はC側からは見えない。
+ */
+typedef struct YonmokuBoard YonmokuBoard;
+
+/**
+ * 探索の打ち切り条件。0の項目は使わない。playoutsとtime_msが両方0なら1000プレイアウト。
+ */
+typedef struct YonmokuLimits {
+  /**
+   * 候補手あたりの試行回数
+   */
+  uint64_t playouts;
+  uint64_t time_ms;
+  /**
+   * 0なら適当に決める
+   */
+  uint64_t seed;
+  /**
+   * YONMOKU_TREE_*
+   */
+  uint32_t tree;
+} YonmokuLimits;
+
+/**
+ * 候補手ごとの統計。勝ち負けは探索した局面の手番側から見る。
+ */
+typedef struct YonmokuMoveStats {
+  uint32_t column;
+  uint64_t visits;
+  uint64_t win;
+  uint64_t draw;
+  uint64_t lose;
+  float win_rate;
+  /**
+   * policyの点数。なければ-1
+   */
+  int32_t prior;
+  enum YonmokuProof proven;
+} YonmokuMoveStats;
+
+/**
+ * 探索結果。movesの先頭n_moves個が強い順に並ぶ。
+ */
+typedef struct YonmokuSearchResult {
+  /**
+   * 最善手の列。置けなければ-1
+   */
+  int32_t best;
+  /**
+   * 王手の応手しか調べていない
+   */
+  bool forced;
+  /**
+   * 使ったseed
+   */
+  uint64_t seed;
+  uint32_t n_moves;
+  struct YonmokuMoveStats moves[YONMOKU_COLUMNS];
+} YonmokuSearchResult;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * 初期局面の盤を作る
+ */
+struct YonmokuBoard *yonmoku_board_new(void);
+
+/**
+ * 局面文字列から盤を作る。読めなければNULL。
+ *
+ * # Safety
+ * positionはNUL終端の文字列か、NULL。
+ */
+struct YonmokuBoard *yonmoku_board_from_position(const char *position);
+
+/**
+ * 盤を複製する。boardがNULLならNULL。
+ *
+ * # Safety
+ * boardはyonmoku_board_*で作った盤か、NULL。
+ */
+struct YonmokuBoard *yonmoku_board_clone(const struct YonmokuBoard *board);
+
+/**
+ * 盤を捨てる。NULLなら何もしない。
+ *
+ * # Safety
+ * boardはyonmoku_board_*で作った盤か、NULL。二度捨ててはいけない。
+ */
+void yonmoku_board_free(struct YonmokuBoard *board);
+
+/**
+ * columnに置く。置けなければ盤は変わらない。
+ *
+ * # Safety
+ * boardはyonmoku_board_*で作った盤か、NULL。
+ */
+enum YonmokuStatus yonmoku_board_play(struct YonmokuBoard *board, uint32_t column);
+
+/**
+ * 置ける列をmovesに書き、その数を返す。終局していれば0。
+ * movesはYONMOKU_COLUMNS個あれば足りる。lenより多い分は書かずに数だけ数える。
+ *
+ * # Safety
+ * boardはyonmoku_board_*で作った盤か、NULL。movesはlen個書ける領域か、lenが0ならNULLでもよい。
+ */
+size_t yonmoku_board_legal_moves(const struct YonmokuBoard *board,
+                                 uint32_t *moves,
+                                 size_t len);
+
+/**
+ * 勝敗が決まったか
+ *
+ * # Safety
+ * boardはyonmoku_board_*で作った盤。
+ */
+enum YonmokuOutcome yonmoku_board_outcome(const struct YonmokuBoard *board);
+
+/**
+ * 手番。黒なら1、白なら2。
+ *
+ * # Safety
+ * boardはyonmoku_board_*で作った盤。
+ */
+int32_t yonmoku_board_side_to_move(const struct YonmokuBoard *board);
+
+/**
+ * 手番側が置けば勝てる列。なければ-1。
+ *
+ * # Safety
+ * boardはyonmoku_board_*で作った盤。
+ */
+int32_t yonmoku_board_win_column(const struct YonmokuBoard *board);
+
+/**
+ * 相手が次に勝てるので、手番側が塞がなければいけない列。なければ-1。
+ *
+ * # Safety
+ * boardはyonmoku_board_*で作った盤。
+ */
+int32_t yonmoku_board_check_column(const struct YonmokuBoard *board);
+
+/**
+ * 局面文字列をbufにNUL終端で書く。終端を除いた長さを返す。
+ * lenが足りなければ書ける分だけ書くので、戻り値+1の大きさで呼び直す。
+ *
+ * # Safety
+ * boardはyonmoku_board_*で作った盤。bufはlen個書ける領域か、lenが0ならNULLでもよい。
+ */
+size_t yonmoku_board_position(const struct YonmokuBoard *board,
+                              char *buf,
+                              size_t len);
+
+/**
+ * 探索して、最善手と候補手ごとの統計をresultに書く。終局していればbestは-1。
+ *
+ * # Safety
+ * boardはyonmoku_board_*で作った盤。limitsとresultは有効な領域。
+ */
+enum YonmokuStatus yonmoku_search(const struct YonmokuBoard *board,
+                                  const struct YonmokuLimits *limits,
+                                  struct YonmokuSearchResult *result);
+
+/**
+ * ライブラリのバージョン。NUL終端の静的な文字列。
+ */
+const char *yonmoku_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* YONMOKU_H */
@@ -0,0 +1,326 @@
+use std::{cmp::Reverse, collections::BinaryHeap, mem::size_of, ops::Range};
+
+use crate::N;
+
+/**
+ * アリーナ内のノードの番号
+ */
+pub type NodeId = u32;
+
+/// まだ展開していない印
+const UNEXPANDED: NodeId = NodeId::MAX;
+/// 上限がないときに最初に確保しておくノード数
+const PREALLOCATE: usize = 1 << 12;
+/// 根の候補手ひとつあたりのノード数の下限。根の子を展開しても刈り込みで半分に収まる。
+const MIN_NODES: usize = 4 * N * N;
+
+/**
+ * ノード数の上限に達したときのふるまい
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WhenFull {
+    /// 展開をやめ、葉からのプレイアウトだけを続ける
+    #[default]
+    Stop,
+    /// 試行回数の少ない部分木を捨てて空きを作る
+    Prune,
+}
+
+/**
+ * 木全体のノード数の上限。根の候補手ごとに等分する。
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryLimit {
+    /// Noneなら上限なし
+    pub nodes: Option<usize>,
+    pub when_full: WhenFull,
+}
+
+impl MemoryLimit {
+    pub fn nodes(nodes: usize, when_full: WhenFull) -> Self {
+        MemoryLimit {
+            nodes: Some(nodes),
+            when_full,
+        }
+    }
+
+    /**
+     * バイト数から上限を決める
+     */
+    pub fn bytes(bytes: usize, when_full: WhenFull) -> Self {
+        MemoryLimit::nodes(bytes / size_of::<Node>(), when_full)
+    }
+
+    fn per_subtree(&self) -> usize {
+        self.nodes
+            .map_or(usize::MAX, |nodes| (nodes / (N * N)).max(MIN_NODES))
+    }
+}
+
+/**
+ * 木のノード。局面は持たず、たどるときに手から作り直す。
+ * 子はアリーナ内で連続して並ぶ。
+ */
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Node {
+    pub n_trial: usize,
+    pub n_win: usize,
+    pub n_lose: usize,
+    first: NodeId,
+    len: u8,
+    hand: u8,
+    policy: u16,
+}
+
+impl Node {
+    pub fn new(hand: usize, policy: usize) -> Self {
+        Node {
+            n_trial: 0,
+            n_win: 0,
+            n_lose: 0,
+            first: UNEXPANDED,
+            len: 0,
+            hand: hand as u8,
+            policy: policy.min(u16::MAX as usize) as u16,
+        }
+    }
+
+    pub fn hand(&self) -> usize {
+        self.hand as usize
+    }
+
+    pub fn policy(&self) -> usize {
+        self.policy as usize
+    }
+
+    pub fn is_expanded(&self) -> bool {
+        self.first != UNEXPANDED
+    }
+
+    pub fn n_draw(&self) -> usize {
+        self.n_trial - self.n_win - self.n_lose
+    }
+
+    pub fn win_rate(&self) -> f32 {
+        (self.n_win as f32 + (self.n_draw() as f32) / 2.0) / (self.n_trial as f32)
+    }
+
+    /**
+     * このノードの手番側から見た結果を足す
+     */
+    pub fn add(&mut self, (n_trial, n_win, n_lose): (usize, usize, usize)) {
+        self.n_trial += n_trial;
+        self.n_win += n_win;
+        self.n_lose += n_lose;
+    }
+}
+
+/**
+ * 根の候補手ひとつ分のノードを入れておく領域。根はROOT。
+ */
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Arena {
+    nodes: Vec<Node>,
+    capacity: usize,
+    when_full: WhenFull,
+}
+
+impl Arena {
+    pub const ROOT: NodeId = 0;
+
+    pub fn new(root: Node, memory: &MemoryLimit) -> Self {
+        let capacity = memory.per_subtree();
+        let mut nodes = Vec::with_capacity(capacity.min(PREALLOCATE));
+        nodes.push(root);
+        Arena {
+            nodes,
+            capacity,
+            when_full: memory.when_full,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn get(&self, id: NodeId) -> &Node {
+        &self.nodes[id as usize]
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id as usize]
+    }
+
+    pub fn root(&self) -> &Node {
+        self.get(Arena::ROOT)
+    }
+
+    /**
+     * 子の番号。展開していなければ空。
+     */
+    pub fn children(&self, id: NodeId) -> Range<NodeId> {
+        let node = self.get(id);
+        if node.is_expanded() {
+            node.first..node.first + node.len as NodeId
+        } else {
+            0..0
+        }
+    }
+
+    fn has_room(&self) -> bool {
+        self.nodes.len() + N * N <= self.capacity
+    }
+
+    /**
+     * idの子を並べはじめる。空きがなければfalse。続けてpush_childで子を足す。
+     */
+    pub fn expand(&mut self, id: NodeId) -> bool {
+        if !self.has_room() {
+            return false;
+        }
+        let first = self.nodes.len() as NodeId;
+        let node = self.get_mut(id);
+        node.first = first;
+        node.len = 0;
+        true
+    }
+
+    pub fn push_child(&mut self, id: NodeId, child: Node) -> NodeId {
+        let child_id = self.nodes.len() as NodeId;
+        let node = self.get_mut(id);
+        debug_assert_eq!(node.first + node.len as NodeId, child_id);
+        node.len += 1;
+        self.nodes.push(child);
+        child_id
+    }
+
+    /**
+     * 次の展開の前に刈り込むべきか
+     */
+    pub fn should_prune(&self) -> bool {
+        self.when_full == WhenFull::Prune && !self.has_room()
+    }
+
+    /**
+     * 試行回数の多いノードから子を残していき、上限の半分に収める。
+     * 残らなかったノードは統計を保ったまま未展開に戻る。
+     */
+    pub fn prune(&mut self) {
+        self.nodes = self.copy(Arena::ROOT, self.capacity / 2);
+    }
+
+    /**
+     * idを根にした部分木を取り出す
+     */
+    pub fn subtree(&self, id: NodeId) -> Arena {
+        Arena {
+            nodes: self.copy(id, self.capacity),
+            capacity: self.capacity,
+            when_full: self.when_full,
+        }
+    }
+
+    fn copy(&self, root: NodeId, budget: usize) -> Vec<Node> {
+        let mut nodes = Vec::with_capacity(self.capacity.min(PREALLOCATE));
+        let mut unexpanded = self.get(root).clone();
+        unexpanded.first = UNEXPANDED;
+        nodes.push(unexpanded);
+
+        // (試行回数, 元の番号, 新しい番号)
+        let mut heap = BinaryHeap::new();
+        heap.push((self.get(root).n_trial, Reverse(root), 0));
+        while let Some((_, Reverse(old), new)) = heap.pop() {
+            let children = self.children(old);
+            if nodes.len() + children.len() > budget {
+                continue;
+            }
+            nodes[new].first = nodes.len() as NodeId;
+            nodes[new].len = children.len() as u8;
+            for child in children {
+                let node = self.get(child);
+                if node.is_expanded() {
+                    heap.push((node.n_trial, Reverse(child), nodes.len()));
+                }
+                let mut node = node.clone();
+                node.first = UNEXPANDED;
+                nodes.push(node);
+            }
+        }
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 根の下にa,bの二つの子、aの下にN*N個の子
+    fn sample(memory: &MemoryLimit) -> Arena {
+        let mut arena = Arena::new(Node::new(0, 0), memory);
+        assert!(arena.expand(Arena::ROOT));
+        let a = arena.push_child(Arena::ROOT, Node::new(1, 0));
+        let b = arena.push_child(Arena::ROOT, Node::new(2, 0));
+        arena.get_mut(a).add((10, 5, 5));
+        arena.get_mut(b).add((3, 1, 2));
+        assert!(arena.expand(a));
+        for index in 0..N * N {
+            arena.push_child(a, Node::new(index, index));
+        }
+        arena
+    }
+
+    #[test]
+    fn test_subtree() {
+        let arena = sample(&MemoryLimit::default());
+        assert_eq!(arena.len(), 3 + N * N);
+        assert!(!arena.should_prune());
+
+        let a = arena.children(Arena::ROOT).start;
+        let subtree = arena.subtree(a);
+        assert_eq!(subtree.len(), 1 + N * N);
+        assert_eq!(subtree.root().n_trial, 10);
+        let hands = subtree
+            .children(Arena::ROOT)
+            .map(|id| subtree.get(id).hand())
+            .collect::<Vec<_>>();
+        assert_eq!(hands, (0..N * N).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_prune() {
+        let memory = MemoryLimit::nodes(MIN_NODES * N * N, WhenFull::Prune);
+        let mut arena = sample(&memory);
+        // 根の子bの下に一本道を伸ばして上限に近づける
+        let b = arena.children(Arena::ROOT).start + 1;
+        let mut id = b;
+        while !arena.should_prune() {
+            assert!(arena.expand(id));
+            id = arena.push_child(id, Node::new(0, 0));
+        }
+        arena.prune();
+        assert!(arena.len() <= MIN_NODES / 2);
+        assert!(!arena.should_prune());
+        // 試行回数の多いaの子は残り、bの統計はそのまま
+        let a = arena.children(Arena::ROOT).start;
+        assert_eq!(arena.children(a).len(), N * N);
+        assert_eq!(arena.get(a + 1).n_trial, 3);
+
+        // Stopなら上限で展開できなくなる
+        let memory = MemoryLimit::nodes(MIN_NODES * N * N, WhenFull::Stop);
+        let mut arena = sample(&memory);
+        let mut id = arena.children(Arena::ROOT).start + 1;
+        while arena.expand(id) {
+            id = arena.push_child(id, Node::new(0, 0));
+        }
+        assert!(!arena.should_prune());
+        assert!(arena.len() <= MIN_NODES);
+    }
+}
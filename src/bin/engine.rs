@@ -0,0 +1,234 @@
+use std::{
+    env,
+    io::{self, BufRead},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use yonmoku::{
+    bitboard::BitBoard,
+    format_index, mctree,
+    protocol::{parse_command, Command},
+    search::{new_seed, SearchLimits, SearchResult, SearchStats},
+    simple_puct::{self, CountPolicy, SimplePolicy},
+};
+
+#[derive(Clone, Copy, Debug)]
+enum Agent {
+    Ucb1,
+    PuctSimple,
+    PuctCount,
+}
+
+struct Engine {
+    board: BitBoard,
+    tree: String,
+    policy: String,
+    /// goのたびにこのseedから探索する
+    seed: u64,
+    search: Option<(SearchLimits, JoinHandle<()>)>,
+}
+
+/**
+ * 探索中の進み具合は、このくらいの間隔で知らせる
+ */
+const INFO_INTERVAL: Duration = Duration::from_secs(1);
+
+fn search(
+    agent: Agent,
+    board: BitBoard,
+    limits: &SearchLimits,
+    seed: u64,
+    progress: impl FnMut(&SearchStats),
+) -> SearchResult {
+    match agent {
+        Agent::Ucb1 => {
+            let mut tree = mctree::McTreeRoot::new(board);
+            tree.set_seed(seed);
+            tree.search_with_progress(limits, progress)
+        }
+        Agent::PuctSimple => {
+            let mut tree = simple_puct::McTreeRoot::<SimplePolicy>::new(board);
+            tree.set_seed(seed);
+            tree.search_with_progress(limits, progress)
+        }
+        Agent::PuctCount => {
+            let mut tree = simple_puct::McTreeRoot::<CountPolicy>::new(board);
+            tree.set_seed(seed);
+            tree.search_with_progress(limits, progress)
+        }
+    }
+}
+
+fn print_stats(stats: &SearchStats) {
+    println!(
+        "info playouts {} pps {:.0} nodes {} depth {} avgdepth {:.2}",
+        stats.playouts,
+        stats.playouts_per_second(),
+        stats.nodes,
+        stats.max_depth,
+        stats.average_depth()
+    );
+}
+
+impl Engine {
+    fn new(seed: u64) -> Self {
+        Engine {
+            board: BitBoard::new(),
+            tree: "puct".to_owned(),
+            policy: "simple".to_owned(),
+            seed,
+            search: None,
+        }
+    }
+
+    fn agent(&self) -> Agent {
+        match (self.tree.as_str(), self.policy.as_str()) {
+            ("ucb1", _) => Agent::Ucb1,
+            (_, "count") => Agent::PuctCount,
+            _ => Agent::PuctSimple,
+        }
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match (name, value) {
+            ("Tree", "puct" | "ucb1") => self.tree = value.to_owned(),
+            ("Policy", "simple" | "count") => self.policy = value.to_owned(),
+            ("Seed", _) => {
+                self.seed = value
+                    .parse()
+                    .map_err(|_| format!("invalid seed: {}", value))?
+            }
+            _ => return Err(format!("invalid option: {} = {}", name, value)),
+        }
+        Ok(())
+    }
+
+    fn go(&mut self, limits: SearchLimits) {
+        self.wait();
+        if self.board.is_won() || self.board.is_full() {
+            println!("bestmove none");
+            return;
+        }
+
+        let agent = self.agent();
+        let board = self.board.clone();
+        let seed = self.seed;
+        let thread_limits = limits.clone();
+        println!("info seed {}", seed);
+        let handle = thread::spawn(move || {
+            let start = Instant::now();
+            let mut last_info = start;
+            let result = search(agent, board, &thread_limits, seed, |stats| {
+                if last_info.elapsed() >= INFO_INTERVAL {
+                    last_info = Instant::now();
+                    print_stats(stats);
+                }
+            });
+            let time = start.elapsed().as_millis();
+            if result.forced {
+                println!("info forced");
+            }
+            print_stats(&result.stats);
+            for (rank, m) in result.moves.iter().enumerate() {
+                println!(
+                    "info multipv {} move {} time {} visits {} winrate {:.1} drawrate {:.1} prior {} proven {} pv {}",
+                    rank + 1,
+                    format_index(m.hand),
+                    time,
+                    m.visits,
+                    m.win_rate() * 100f32,
+                    m.draw_rate() * 100f32,
+                    m.prior.map_or("none".to_owned(), |p| p.to_string()),
+                    m.proven.map_or("none".to_owned(), |p| p.to_string()),
+                    m.pv.iter().map(|&h| format_index(h)).collect::<Vec<_>>().join(" ")
+                );
+            }
+            match result.best() {
+                Some(m) => println!("bestmove {}", format_index(m.hand)),
+                None => println!("bestmove none"),
+            }
+        });
+        self.search = Some((limits, handle));
+    }
+
+    /**
+     * 探索が終わるのを待つ。終わらない探索は止める。
+     */
+    fn wait(&mut self) {
+        if let Some((limits, handle)) = self.search.take() {
+            if limits.is_infinite() {
+                limits.stop();
+            }
+            handle.join().unwrap();
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some((limits, handle)) = self.search.take() {
+            limits.stop();
+            handle.join().unwrap();
+        }
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: engine [--seed N]");
+    std::process::exit(1);
+}
+
+fn main() -> io::Result<()> {
+    let mut args = env::args().skip(1);
+    let seed = match args.next().as_deref() {
+        None => new_seed(),
+        Some("--seed") => args
+            .next()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_else(|| usage()),
+        Some(_) => usage(),
+    };
+    let mut engine = Engine::new(seed);
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_command(&line) {
+            Ok(Command::Hello) => {
+                println!("id name yonmoku");
+                println!("option name Tree type combo default puct var puct var ucb1");
+                println!("option name Policy type combo default simple var simple var count");
+                println!("option name Seed type string default {}", engine.seed);
+                println!("yonmokuok");
+            }
+            Ok(Command::IsReady) => println!("readyok"),
+            Ok(Command::SetOption { name, value }) => {
+                if let Err(e) = engine.set_option(&name, &value) {
+                    println!("error {}", e);
+                }
+            }
+            Ok(Command::NewGame) => {
+                engine.wait();
+                engine.board = BitBoard::new();
+            }
+            Ok(Command::Position(board)) => {
+                engine.wait();
+                engine.board = board;
+            }
+            Ok(Command::Go(limits)) => engine.go(limits),
+            Ok(Command::Stop) => engine.stop(),
+            Ok(Command::Show) => {
+                println!("info position {}", engine.board.position_string());
+                print!("{}", engine.board);
+            }
+            Ok(Command::Quit) => {
+                engine.stop();
+                break;
+            }
+            Err(e) => println!("error {}", e),
+        }
+    }
+    engine.wait();
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use std::{env, time::Instant};
+
+use yonmoku::{bitboard::BitBoard, board::ArrayBoard, perft::perft};
+
+fn usage() -> ! {
+    eprintln!("usage: perft DEPTH [--symmetry] [--array] [--moves INDEX,INDEX,...]");
+    std::process::exit(1);
+}
+
+fn main() {
+    let mut depth = None;
+    let mut symmetry = false;
+    let mut array = false;
+    let mut board = BitBoard::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--symmetry" => symmetry = true,
+            "--array" => array = true,
+            "--moves" => {
+                let moves = args.next().unwrap_or_else(|| usage());
+                for index in moves.split(',').filter(|s| !s.is_empty()) {
+                    let index = index.parse::<usize>().unwrap_or_else(|_| usage());
+                    board = board.try_play(index).unwrap_or_else(|e| {
+                        eprintln!("illegal move {}: {}", index, e);
+                        std::process::exit(1);
+                    });
+                }
+            }
+            _ => depth = Some(arg.parse::<usize>().unwrap_or_else(|_| usage())),
+        }
+    }
+    let depth = depth.unwrap_or_else(|| usage());
+
+    println!("{}", board.render().auto_color());
+    let start = Instant::now();
+    let counts = if array {
+        perft(&ArrayBoard::from(&board), depth, symmetry)
+    } else {
+        perft(&board, depth, symmetry)
+    };
+
+    println!("ply\tpaths\tpositions\twin paths\twin positions\tdraw paths");
+    for count in counts {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            count.ply,
+            count.paths,
+            count.positions,
+            count.win_paths,
+            count.win_positions,
+            count.draw_paths
+        );
+    }
+    println!("time: {:.3}s", start.elapsed().as_secs_f64());
+}
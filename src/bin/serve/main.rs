@@ -0,0 +1,513 @@
+use std::{
+    collections::HashMap,
+    env,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    str::FromStr,
+};
+
+use yonmoku::{
+    board::Player,
+    format_index,
+    game::{Game, GameResult},
+    parse_index,
+    search::{derive_seed, new_seed, MoveStats, SearchLimits, SearchResult},
+    simple_puct::{McTreeRoot, SimplePolicy},
+    N,
+};
+
+const INDEX_HTML: &str = include_str!("index.html");
+
+/**
+ * これより長いボディのリクエストは断る
+ */
+const MAX_BODY: u64 = 64 * 1024;
+
+/**
+ * リクエスト行とヘッダーを合わせた長さの上限
+ */
+const MAX_HEAD: u64 = 8 * 1024;
+
+struct Server {
+    game: Game,
+    human: Player,
+    n_try: usize,
+    seed: u64,
+    /// the last CPU move
+    cpu: Option<MoveStats>,
+    cpu_forced: bool,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+}
+
+/**
+ * 読み終える前に断ったリクエスト
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Rejection {
+    BadRequest,
+    HeadTooLarge,
+    BodyTooLarge,
+}
+
+impl Rejection {
+    fn status(self) -> &'static str {
+        match self {
+            Rejection::BadRequest => "400 Bad Request",
+            Rejection::HeadTooLarge => "431 Request Header Fields Too Large",
+            Rejection::BodyTooLarge => "413 Payload Too Large",
+        }
+    }
+
+    fn message(self) -> &'static str {
+        match self {
+            Rejection::BadRequest => "malformed request",
+            Rejection::HeadTooLarge => "request headers are too large",
+            Rejection::BodyTooLarge => "request body is too large",
+        }
+    }
+}
+
+fn player_str(player: Player) -> &'static str {
+    match player {
+        Player::Black => "b",
+        Player::White => "w",
+    }
+}
+
+fn move_json(m: &MoveStats, forced: bool) -> String {
+    format!(
+        "{{\"move\":\"{}\",\"rate\":{:.1},\"visits\":{},\"forced\":{}}}",
+        format_index(m.hand),
+        m.win_rate() * 100f32,
+        m.visits,
+        forced
+    )
+}
+
+/**
+ * JSONの文字列にする
+ */
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c < ' ' => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/**
+ * %XXをバイトに戻す。壊れた%やUTF-8にならないバイト列ならNone
+ */
+fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = vec![];
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/**
+ * 一行読む。上限に達して行が終わらなければNone
+ */
+fn read_head_line(head: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    head.read_line(&mut line)?;
+    Ok((line.is_empty() || line.ends_with('\n')).then_some(line))
+}
+
+fn read_request(stream: &mut TcpStream) -> io::Result<Result<Request, Rejection>> {
+    let mut reader = BufReader::new(stream);
+    let mut head = (&mut reader).take(MAX_HEAD);
+    let Some(line) = read_head_line(&mut head)? else {
+        return Ok(Err(Rejection::HeadTooLarge));
+    };
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_owned();
+    let target = parts.next().unwrap_or_default();
+
+    // ヘッダーは読み捨てる。ボディも使わない。
+    let mut content_length = 0;
+    loop {
+        let Some(header) = read_head_line(&mut head)? else {
+            return Ok(Err(Rejection::HeadTooLarge));
+        };
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return Ok(Err(Rejection::BodyTooLarge));
+    }
+    io::copy(&mut reader.take(content_length), &mut io::sink())?;
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| Some((percent_decode(k)?, percent_decode(v)?)))
+        .collect::<Option<_>>();
+    let (Some(path), Some(query)) = (percent_decode(path), query) else {
+        return Ok(Err(Rejection::BadRequest));
+    };
+    Ok(Ok(Request {
+        method,
+        path,
+        query,
+    }))
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+impl Server {
+    fn new(n_try: usize, seed: u64) -> Self {
+        Server {
+            game: Game::new(),
+            human: Player::Black,
+            n_try,
+            seed,
+            cpu: None,
+            cpu_forced: false,
+        }
+    }
+
+    fn search(&self) -> SearchResult {
+        let stone = self.game.ply();
+        let limits = SearchLimits::playouts(self.n_try * (1 + stone * stone / 50));
+        let mut tree = McTreeRoot::<SimplePolicy>::new(self.game.board().clone());
+        tree.set_seed(derive_seed(self.seed, stone as u64));
+        tree.search(&limits)
+    }
+
+    fn cpu_move(&mut self) {
+        if self.game.is_over() {
+            return;
+        }
+        let result = self.search();
+        if let Some(m) = result.best() {
+            self.game.play(m.hand).unwrap();
+            self.cpu = Some(m.clone());
+            self.cpu_forced = result.forced;
+        }
+    }
+
+    fn state_json(&self) -> String {
+        let board = self.game.board();
+        let result = match self.game.result() {
+            Some(GameResult::Win(player)) => format!("\"{}\"", player_str(player)),
+            Some(GameResult::Draw) => "\"draw\"".to_owned(),
+            None => "null".to_owned(),
+        };
+        // 完成したラインのマス。"i,j,k"
+        let line = self
+            .game
+            .winning_line()
+            .unwrap_or_default()
+            .iter()
+            .map(|&(index_2d, k)| format!("\"{},{}\"", format_index(index_2d), k))
+            .collect::<Vec<_>>()
+            .join(",");
+        let columns = board
+            .position_string()
+            .split('/')
+            .map(|c| format!("\"{}\"", c))
+            .collect::<Vec<_>>()
+            .join(",");
+        let moves = self
+            .game
+            .moves()
+            .iter()
+            .map(|&m| format!("\"{}\"", format_index(m)))
+            .collect::<Vec<_>>()
+            .join(",");
+        let cpu = match &self.cpu {
+            Some(m) => move_json(m, self.cpu_forced),
+            None => "null".to_owned(),
+        };
+        format!(
+            "{{\"n\":{},\"columns\":[{}],\"next\":\"{}\",\"human\":\"{}\",\"result\":{},\"line\":[{}],\"moves\":[{}],\"cpu\":{}}}",
+            N,
+            columns,
+            player_str(board.next_player()),
+            player_str(self.human),
+            result,
+            line,
+            moves,
+            cpu
+        )
+    }
+
+    fn handle(&mut self, request: &Request) -> Result<String, String> {
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/api/state") => Ok(self.state_json()),
+            ("POST", "/api/new") => {
+                *self = Server::new(self.n_try, self.seed);
+                if request.query.get("first").map(String::as_str) == Some("cpu") {
+                    self.human = Player::White;
+                    self.cpu_move();
+                }
+                Ok(self.state_json())
+            }
+            ("POST", "/api/move") => {
+                if self.game.is_over() {
+                    return Err("game is over".to_owned());
+                }
+                if self.game.side_to_move() != self.human {
+                    return Err("not your turn".to_owned());
+                }
+                let index = request
+                    .query
+                    .get("column")
+                    .and_then(|c| parse_index(c))
+                    .ok_or("column must be i,j")?;
+                self.game.play(index).map_err(|e| e.to_string())?;
+                self.cpu = None;
+                self.cpu_move();
+                Ok(self.state_json())
+            }
+            ("POST", "/api/takeback") => {
+                // 人間の最後の手まで戻す
+                let first_human = if self.human == Player::Black { 0 } else { 1 };
+                while self.game.ply() > first_human {
+                    self.game.undo();
+                    if self.game.side_to_move() == self.human {
+                        break;
+                    }
+                }
+                self.cpu = None;
+                Ok(self.state_json())
+            }
+            ("GET", "/api/hint") => {
+                if self.game.is_over() {
+                    return Err("game is over".to_owned());
+                }
+                let result = self.search();
+                Ok(move_json(result.best().unwrap(), result.forced))
+            }
+            ("GET", "/api/eval") => {
+                if self.game.is_over() {
+                    return Err("game is over".to_owned());
+                }
+                let result = self.search();
+                let moves = result
+                    .moves
+                    .iter()
+                    .map(|m| move_json(m, result.forced))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                Ok(format!("{{\"moves\":[{}]}}", moves))
+            }
+            _ => Err(format!("no route for {} {}", request.method, request.path)),
+        }
+    }
+
+    fn serve(&mut self, stream: &mut TcpStream) -> io::Result<()> {
+        let request = match read_request(stream)? {
+            Ok(request) => request,
+            Err(rejection) => {
+                let body = format!("{{\"error\":{}}}", json_string(rejection.message()));
+                return respond(stream, rejection.status(), "application/json", &body);
+            }
+        };
+        if request.method == "GET" && request.path == "/" {
+            return respond(stream, "200 OK", "text/html; charset=utf-8", INDEX_HTML);
+        }
+        match self.handle(&request) {
+            Ok(body) => respond(stream, "200 OK", "application/json", &body),
+            Err(e) => respond(
+                stream,
+                "400 Bad Request",
+                "application/json",
+                &format!("{{\"error\":{}}}", json_string(&e)),
+            ),
+        }
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: serve [--port PORT] [--playouts N] [--seed N]");
+    std::process::exit(1);
+}
+
+fn value<T: FromStr>(args: &mut impl Iterator<Item = String>) -> T {
+    args.next()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| usage())
+}
+
+fn main() -> io::Result<()> {
+    let mut port: u16 = 8080;
+    let mut n_try = 50_000;
+    let mut seed = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => port = value(&mut args),
+            "--playouts" => n_try = value(&mut args),
+            "--seed" => seed = Some(value(&mut args)),
+            _ => usage(),
+        }
+    }
+
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let seed = seed.unwrap_or_else(new_seed);
+    println!("seed: {}", seed);
+    println!("listening on http://127.0.0.1:{}/", port);
+    let mut server = Server::new(n_try, seed);
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        if let Err(e) = server.serve(&mut stream) {
+            eprintln!("{}", e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn request(method: &str, path: &str, query: &[(&str, &str)]) -> Request {
+        Request {
+            method: method.to_owned(),
+            path: path.to_owned(),
+            query: query
+                .iter()
+                .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+                .collect(),
+        }
+    }
+
+    /**
+     * 一つのリクエストを送って、応答をそのまま返す
+     */
+    fn exchange(server: &mut Server, request: &'static str) -> String {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+        let (mut stream, _) = listener.accept().unwrap();
+        server.serve(&mut stream).unwrap();
+        drop(stream);
+        client.join().unwrap()
+    }
+
+    #[test]
+    fn test_handle() {
+        let mut server = Server::new(10, 0);
+        let state = server.handle(&request("GET", "/api/state", &[])).unwrap();
+        assert!(state.contains("\"next\":\"b\""));
+
+        let state = server
+            .handle(&request("POST", "/api/move", &[("column", "0,0")]))
+            .unwrap();
+        assert!(state.contains("\"moves\":[\"0,0\","));
+        assert!(server
+            .handle(&request("POST", "/api/move", &[("column", "9,9")]))
+            .is_err());
+        assert!(server.handle(&request("GET", "/nowhere", &[])).is_err());
+    }
+
+    #[test]
+    fn test_serve() {
+        let mut server = Server::new(10, 0);
+        let response = exchange(&mut server, "GET /api/state HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+
+        let response = exchange(
+            &mut server,
+            "POST /api/new?first=\"x HTTP/1.1\r\nContent-Length: 3\r\n\r\nabc",
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+
+        let response = exchange(&mut server, "GET /a\"b\\c HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(response.ends_with("{\"error\":\"no route for GET /a\\\"b\\\\c\"}"));
+
+        let response = exchange(
+            &mut server,
+            "POST /api/new HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large"));
+
+        let response = exchange(&mut server, "POST /api/move?column=0%2x0 HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    }
+
+    #[test]
+    fn test_head_limit() {
+        let mut server = Server::new(10, 0);
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            // ちょうど上限の長さで、ヘッダーが終わらない
+            let mut head = b"GET /api/state HTTP/1.1\r\nX-Long: ".to_vec();
+            head.resize(MAX_HEAD as usize, b'a');
+            stream.write_all(&head).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+        let (mut stream, _) = listener.accept().unwrap();
+        server.serve(&mut stream).unwrap();
+        drop(stream);
+        let response = client.join().unwrap();
+        assert!(response.starts_with("HTTP/1.1 431"), "{}", response);
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("1%2C2%2c3"), Some("1,2,3".to_owned()));
+        assert_eq!(percent_decode("%e3%81%82"), Some("あ".to_owned()));
+        assert_eq!(percent_decode("%2"), None);
+        assert_eq!(percent_decode("%zz"), None);
+        assert_eq!(percent_decode("%ff"), None);
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+    }
+}
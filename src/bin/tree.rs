@@ -0,0 +1,86 @@
+use std::env;
+
+use yonmoku::{
+    arena::{MemoryLimit, WhenFull},
+    export::ExportOptions,
+    game::Game,
+    mctree,
+    search::{new_seed, SearchLimits},
+    simple_puct::{self, SimplePolicy},
+};
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: tree [--ucb1] [--playouts N] [--depth D] [--min-visits V] [--nodes N] [--prune] [--seed N] [--stats] [--json] [--moves \"a1 b2 ...\"]"
+    );
+    std::process::exit(1);
+}
+
+fn number(arg: Option<String>) -> usize {
+    arg.and_then(|s| s.parse().ok()).unwrap_or_else(|| usage())
+}
+
+fn main() {
+    let mut ucb1 = false;
+    let mut json = false;
+    let mut stats = false;
+    let mut playouts = 1_000;
+    let mut options = ExportOptions::default();
+    let mut memory = MemoryLimit::default();
+    let mut seed = None;
+    let mut game = Game::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ucb1" => ucb1 = true,
+            "--json" => json = true,
+            "--stats" => stats = true,
+            "--playouts" => playouts = number(args.next()),
+            "--depth" => options.depth = number(args.next()),
+            "--min-visits" => options.min_visits = number(args.next()),
+            "--nodes" => memory.nodes = Some(number(args.next())),
+            "--prune" => memory.when_full = WhenFull::Prune,
+            "--seed" => seed = Some(number(args.next()) as u64),
+            "--moves" => {
+                let record = args.next().unwrap_or_else(|| usage());
+                game = Game::from_record(&record.replace(',', " ")).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                });
+            }
+            _ => usage(),
+        }
+    }
+    if game.is_over() {
+        eprintln!("the game is already over");
+        std::process::exit(1);
+    }
+
+    // 標準出力は図に使うので、seedは標準エラーへ
+    let seed = seed.unwrap_or_else(new_seed);
+    eprintln!("seed: {}", seed);
+
+    let limits = SearchLimits::playouts(playouts);
+    let board = game.board().clone();
+    let (node, result) = if ucb1 {
+        let mut tree = mctree::McTreeRoot::with_memory(board, memory);
+        tree.set_seed(seed);
+        let result = tree.search(&limits);
+        (tree.export(&options), result)
+    } else {
+        let mut tree = simple_puct::McTreeRoot::<SimplePolicy>::with_memory(board, memory);
+        tree.set_seed(seed);
+        let result = tree.search(&limits);
+        (tree.export(&options), result)
+    };
+    if stats {
+        eprintln!("{}", result.stats);
+    }
+
+    if json {
+        println!("{}", node.to_json());
+    } else {
+        print!("{}", node.to_dot());
+    }
+}
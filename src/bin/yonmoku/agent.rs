@@ -0,0 +1,187 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use rand::Rng;
+use yonmoku::{
+    game::Game,
+    mctree,
+    search::{derive_seed, rng_stream, SearchLimits, SearchResult, SearchStats},
+    simple_puct::{self, CountPolicy, SimplePolicy},
+};
+
+/**
+ * 手を選ぶ方法
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AgentKind {
+    Ucb1,
+    Puct,
+    /// 王手には応じ、それ以外は一様に選ぶ
+    Random,
+}
+
+/**
+ * PUCTで使うPolicy
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PolicyKind {
+    Simple,
+    Count,
+}
+
+impl FromStr for AgentKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ucb1" => Ok(AgentKind::Ucb1),
+            "puct" => Ok(AgentKind::Puct),
+            "random" => Ok(AgentKind::Random),
+            _ => Err(format!("unknown agent: {} (ucb1, puct, random)", s)),
+        }
+    }
+}
+
+impl FromStr for PolicyKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "simple" => Ok(PolicyKind::Simple),
+            "count" => Ok(PolicyKind::Count),
+            _ => Err(format!("unknown policy: {} (simple, count)", s)),
+        }
+    }
+}
+
+/**
+ * 一手あたりの探索量。playoutsは候補手あたりの試行回数で、growthが0でなければ
+ * stone手目でplayouts * (1 + stone * stone / growth)に増やす。timeがあれば時間でも打ち切る。
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Budget {
+    pub playouts: usize,
+    pub growth: usize,
+    pub time: Option<Duration>,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget {
+            playouts: 50_000,
+            growth: 24,
+            time: None,
+        }
+    }
+}
+
+impl Budget {
+    pub fn limits(&self, stone: usize) -> SearchLimits {
+        let playouts = match (stone * stone).checked_div(self.growth) {
+            Some(extra) => self.playouts * (1 + extra),
+            None => self.playouts,
+        };
+        SearchLimits {
+            playouts: Some(playouts),
+            time: self.time,
+            ..Default::default()
+        }
+    }
+}
+
+/**
+ * CPUの選んだ手
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct Choice {
+    pub hand: usize,
+    /// 手番側から見た勝率。Randomは出さない。
+    pub rate: Option<f32>,
+    pub stats: SearchStats,
+}
+
+/**
+ * 対局するCPU
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Agent {
+    pub kind: AgentKind,
+    pub policy: PolicyKind,
+}
+
+impl Agent {
+    pub fn new(kind: AgentKind, policy: PolicyKind) -> Self {
+        Agent { kind, policy }
+    }
+
+    /**
+     * 今の局面を探索する。seedは手数ごとに変える。Randomは探索しないのでNone。
+     */
+    pub fn search(&self, game: &Game, budget: &Budget, seed: u64) -> Option<SearchResult> {
+        let board = game.board();
+        let limits = budget.limits(game.ply());
+        let seed = derive_seed(seed, game.ply() as u64);
+        let result = match (self.kind, self.policy) {
+            (AgentKind::Random, _) => return None,
+            (AgentKind::Ucb1, _) => {
+                let mut tree = mctree::McTreeRoot::new(board.clone());
+                tree.set_seed(seed);
+                tree.search(&limits)
+            }
+            (AgentKind::Puct, PolicyKind::Simple) => {
+                let mut tree = simple_puct::McTreeRoot::<SimplePolicy>::new(board.clone());
+                tree.set_seed(seed);
+                tree.search(&limits)
+            }
+            (AgentKind::Puct, PolicyKind::Count) => {
+                let mut tree = simple_puct::McTreeRoot::<CountPolicy>::new(board.clone());
+                tree.set_seed(seed);
+                tree.search(&limits)
+            }
+        };
+        Some(result)
+    }
+
+    pub fn choose(&self, game: &Game, budget: &Budget, seed: u64) -> Option<Choice> {
+        if self.kind == AgentKind::Random {
+            let mut rng = rng_stream(seed, game.ply() as u64);
+            return random_choose(game, &mut rng).map(|hand| Choice {
+                hand,
+                rate: None,
+                stats: SearchStats::default(),
+            });
+        }
+        let result = self.search(game, budget, seed)?;
+        let best = result.best()?;
+        Some(Choice {
+            hand: best.hand,
+            rate: Some(best.win_rate()),
+            stats: result.stats,
+        })
+    }
+}
+
+/**
+ * ランダム選択。ただし王手には必ず応手する。
+ */
+fn random_choose(game: &Game, rng: &mut impl Rng) -> Option<usize> {
+    if let Some(hand) = game.board().check_index() {
+        return Some(hand);
+    }
+    let moves = game.legal_moves();
+    if moves.is_empty() {
+        None
+    } else {
+        Some(moves[rng.gen_range(0..moves.len())])
+    }
+}
+
+impl fmt::Display for Agent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.kind, self.policy) {
+            (AgentKind::Ucb1, _) => f.write_str("ucb1"),
+            (AgentKind::Puct, PolicyKind::Simple) => f.write_str("puct:simple"),
+            (AgentKind::Puct, PolicyKind::Count) => f.write_str("puct:count"),
+            (AgentKind::Random, _) => f.write_str("random"),
+        }
+    }
+}
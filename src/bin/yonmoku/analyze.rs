@@ -0,0 +1,98 @@
+use std::io;
+
+use yonmoku::{
+    format_column,
+    game::{Game, GameResult},
+    parse_column,
+    search::SearchResult,
+};
+
+use crate::{
+    agent::{AgentKind, Budget},
+    options::Options,
+    prompt, show, usage,
+};
+
+/**
+ * 上位k手の表
+ */
+fn print_result(result: &SearchResult, k: usize) {
+    if result.forced {
+        println!("forced: answering a check");
+    }
+    println!("rank\tmove\tvisits\twin%\tdraw%\tlose%\tprior\tproven\tpv");
+    for (rank, m) in result.moves.iter().take(k).enumerate() {
+        println!(
+            "{}\t{}\t{}\t{:.1}\t{:.1}\t{:.1}\t{}\t{}\t{}",
+            rank + 1,
+            format_column(m.hand),
+            m.visits,
+            m.win_rate() * 100f32,
+            m.draw_rate() * 100f32,
+            m.lose_rate() * 100f32,
+            m.prior.map_or("-".to_owned(), |p| p.to_string()),
+            m.proven.map_or("-".to_owned(), |p| p.to_string()),
+            m.pv.iter()
+                .take(8)
+                .map(|&h| format_column(h))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+    }
+}
+
+/**
+ * 両方の手を入力し、そのたびに次の手番から見た候補手を表示する
+ */
+pub fn run(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let mut options = Options {
+        budget: Budget {
+            growth: 50,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    while let Some(arg) = args.next() {
+        if !options.parse(&arg, &mut args) {
+            usage();
+        }
+    }
+    if options.agent.kind == AgentKind::Random {
+        eprintln!("analyze needs a searching agent (ucb1 or puct)");
+        usage();
+    }
+    let seed = options.start();
+    let mut game = Game::new();
+
+    while let Some(buffer) = prompt("a1..d4? [M]atta?")? {
+        if buffer == "M" {
+            if game.ply() >= 2 {
+                game.undo();
+                game.undo();
+                show(&game);
+            }
+            continue;
+        }
+        let Some(index) = parse_column(&buffer) else {
+            continue;
+        };
+        if let Err(e) = game.play(index) {
+            println!("{}", e);
+            continue;
+        }
+        show(&game);
+        match game.result() {
+            Some(GameResult::Win(player)) => println!("{:?} wins", player),
+            Some(GameResult::Draw) => println!("Draw"),
+            None => {
+                if let Some(result) = options.agent.search(&game, &options.budget, seed) {
+                    print_result(&result, options.top);
+                    if options.stats {
+                        println!("{}", result.stats);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
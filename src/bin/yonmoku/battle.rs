@@ -0,0 +1,158 @@
+use yonmoku::{
+    board::Player,
+    game::{Game, GameResult},
+    search::{derive_seed, SearchStats},
+};
+
+use crate::{
+    agent::{Agent, Budget, PolicyKind},
+    options::Options,
+    usage,
+};
+
+fn parse(mut options: Options, mut args: impl Iterator<Item = String>) -> Options {
+    while let Some(arg) = args.next() {
+        if !options.parse(&arg, &mut args) {
+            usage();
+        }
+    }
+    options
+}
+
+/**
+ * 一局打つ。blackとwhiteはそれぞれの手番のCPU。statsに先手と後手の探索の統計を足す。
+ */
+pub fn play(
+    black: Agent,
+    white: Agent,
+    budget: &Budget,
+    seed: u64,
+    stats: &mut [SearchStats; 2],
+) -> Game {
+    let mut game = Game::new();
+    game.play_out(|game| {
+        let (agent, color) = match game.side_to_move() {
+            Player::Black => (black, 0),
+            Player::White => (white, 1),
+        };
+        let choice = agent.choose(game, budget, seed).expect("no legal move");
+        stats[color] = stats[color].merge(choice.stats);
+        choice.hand
+    })
+    .unwrap();
+    game
+}
+
+/**
+ * agentをsideの手番に置いてgames局打ち、勝率を表示する
+ */
+fn battle(options: &Options, side: Player, seed: u64) {
+    let mut n_win = 0;
+    let mut n_draw = 0;
+    let mut stats = [SearchStats::default(); 2];
+    let (black, white) = match side {
+        Player::Black => (options.agent, options.opponent),
+        Player::White => (options.opponent, options.agent),
+    };
+    for i in 0..options.games {
+        let game = play(
+            black,
+            white,
+            &options.budget,
+            derive_seed(seed, i as u64),
+            &mut stats,
+        );
+        match game.result() {
+            Some(GameResult::Win(player)) if player == side => n_win += 1,
+            Some(GameResult::Draw) => n_draw += 1,
+            _ => (),
+        }
+    }
+    let games = options.games.max(1) as f64;
+    println!(
+        "{} as {:?} vs {}: win_rate: {:.4}, draw_rate: {:.4}",
+        options.agent,
+        side,
+        options.opponent,
+        n_win as f64 / games,
+        n_draw as f64 / games
+    );
+    if options.stats {
+        println!("  black {}: {}", black, stats[0]);
+        println!("  white {}: {}", white, stats[1]);
+    }
+}
+
+/**
+ * agentとopponentを先後入れ替えて戦わせる。--sideがあればその手番だけ。
+ */
+pub fn run_match(args: impl Iterator<Item = String>) {
+    let mut options = Options::default();
+    options.agent.policy = PolicyKind::Count;
+    let options = parse(options, args);
+    let seed = options.start();
+
+    let sides = match options.side {
+        Some(side) => vec![side],
+        None => vec![Player::Black, Player::White],
+    };
+    for side in sides {
+        let stream = match side {
+            Player::Black => 1,
+            Player::White => 2,
+        };
+        battle(&options, side, derive_seed(seed, stream));
+    }
+}
+
+/**
+ * agent同士で打ち、棋譜と先手の勝率を表示する
+ */
+pub fn run_selfplay(args: impl Iterator<Item = String>) {
+    // 多くの局を打つので、既定の探索量は少なめ
+    let options = Options {
+        budget: Budget {
+            playouts: 5_000,
+            growth: 16,
+            time: None,
+        },
+        ..Default::default()
+    };
+    let options = parse(options, args);
+    let seed = options.start();
+
+    let mut n_win = 0;
+    let mut n_draw = 0;
+    let mut stats = [SearchStats::default(); 2];
+    for i in 0..options.games {
+        let game = play(
+            options.agent,
+            options.agent,
+            &options.budget,
+            derive_seed(seed, i as u64),
+            &mut stats,
+        );
+        let result = match game.result() {
+            Some(GameResult::Win(Player::Black)) => {
+                n_win += 1;
+                "black wins"
+            }
+            Some(GameResult::Win(Player::White)) => "white wins",
+            _ => {
+                n_draw += 1;
+                "draw"
+            }
+        };
+        println!("{}: {} ({})", i + 1, game.to_record(), result);
+    }
+    let games = options.games.max(1) as f64;
+    println!(
+        "{} sente win_rate: {:.4}, draw_rate: {:.4}",
+        options.agent,
+        n_win as f64 / games,
+        n_draw as f64 / games
+    );
+    if options.stats {
+        println!("{}", stats[0].merge(stats[1]));
+    }
+}
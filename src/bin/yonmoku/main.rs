@@ -0,0 +1,159 @@
+use std::{env, io};
+
+use yonmoku::game::Game;
+
+mod agent;
+mod analyze;
+mod battle;
+mod options;
+mod play;
+mod prove;
+mod puzzles;
+mod session;
+mod solve;
+mod train;
+mod tui;
+mod watch;
+
+/**
+ * 以前の個別のバイナリと同じ設定。(名前, サブコマンド, フラグ)
+ */
+const PRESETS: &[(&str, &str, &str)] = &[
+    (
+        "hyoka",
+        "analyze",
+        "--agent puct --policy simple --playouts 50000 --growth 50 --top 5",
+    ),
+    (
+        "watch",
+        "watch",
+        "--agent ucb1 --opponent ucb1 --playouts 50000 --growth 24",
+    ),
+    (
+        "watch_random",
+        "watch",
+        "--agent ucb1 --side white --opponent random --playouts 50000 --growth 24",
+    ),
+    (
+        "battle_cpu",
+        "match",
+        "--agent puct --policy count --opponent puct --opponent-policy simple --playouts 50000 --growth 24 --games 100",
+    ),
+    (
+        "random_battle",
+        "match",
+        "--agent puct --policy count --opponent random --playouts 5000 --growth 16 --games 10",
+    ),
+];
+
+const USAGE: &str = "\
+usage: yonmoku [play] [--plain] [--level easy|normal|hard|max] [--blunder P] [--playouts N] [--guard]
+               [--memory MB] [--prune] [--side black|white] [--seed N] [--threads N] [--resume FILE]
+       yonmoku analyze [AGENT] [BUDGET] [--top K] [--stats]
+       yonmoku watch [AGENT] [OPPONENT] [BUDGET] [--side black|white] [--stats]
+       yonmoku match [AGENT] [OPPONENT] [BUDGET] [--games N] [--side black|white] [--stats]
+       yonmoku selfplay [AGENT] [BUDGET] [--games N] [--stats]
+       yonmoku prove [--moves \"a1 b2 ...\" | --position POSITION] [--nodes N] [--export FILE]
+       yonmoku solve [--moves \"a1 b2 ...\" | --position POSITION] [--checkpoint FILE] [--interval SECS]
+                     [--slice N] [--memory MB] [--nodes N] [--export FILE]
+       yonmoku verify FILE
+       yonmoku puzzles [AGENT] [BUDGET] [--games N] [--win-in K] [--out FILE]
+       yonmoku train FILE [--shuffle] [--seed N]
+       yonmoku --preset NAME [FLAGS]
+
+AGENT     --agent ucb1|puct|random  --policy simple|count
+OPPONENT  --opponent ucb1|puct|random  --opponent-policy simple|count
+BUDGET    --playouts N  --growth D  --time MS
+          playouts per candidate move, times 1 + stone^2 / D unless D is 0
+all       --seed N  --threads N
+--stats   print playouts/s, nodes, depth, expansions and time per phase";
+
+pub fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    eprintln!();
+    eprintln!("presets:");
+    for (name, command, flags) in PRESETS {
+        eprintln!("  {:<14}{} {}", name, command, flags);
+    }
+    std::process::exit(1);
+}
+
+/**
+ * 入力の終わりならNone
+ */
+pub fn prompt(msg: &str) -> io::Result<Option<String>> {
+    println!("{}", msg);
+    let mut buffer = String::new();
+    if io::stdin().read_line(&mut buffer)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(buffer.trim().to_owned()))
+}
+
+pub fn show(game: &Game) {
+    println!(
+        "{}",
+        game.board()
+            .render()
+            .last(game.last_move())
+            .winning_line()
+            .auto_color()
+    );
+}
+
+fn main() -> io::Result<()> {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    // プリセットはサブコマンドとフラグに展開する。後ろに書いたフラグが優先される。
+    if args.first().is_some_and(|arg| arg == "--preset") {
+        let name = args.get(1).unwrap_or_else(|| usage());
+        let Some((_, command, flags)) = PRESETS.iter().find(|(n, _, _)| n == name) else {
+            eprintln!("unknown preset: {}", name);
+            usage();
+        };
+        let rest = args.split_off(2);
+        args = [*command]
+            .into_iter()
+            .chain(flags.split_whitespace())
+            .map(str::to_owned)
+            .chain(rest)
+            .collect();
+    }
+
+    let mut args = args.into_iter().peekable();
+    let command = match args.peek() {
+        Some(arg) if !arg.starts_with('-') => args.next().unwrap(),
+        _ => "play".to_owned(),
+    };
+    match command.as_str() {
+        "play" => play::run(args),
+        "analyze" => analyze::run(args),
+        "watch" => {
+            watch::run(args);
+            Ok(())
+        }
+        "match" => {
+            battle::run_match(args);
+            Ok(())
+        }
+        "selfplay" => {
+            battle::run_selfplay(args);
+            Ok(())
+        }
+        "prove" => {
+            prove::run(args);
+            Ok(())
+        }
+        "solve" => solve::run(args),
+        "puzzles" => puzzles::run(args),
+        "train" => train::run(args),
+        "verify" => {
+            prove::run_verify(args);
+            Ok(())
+        }
+        "help" => usage(),
+        _ => {
+            eprintln!("unknown command: {}", command);
+            usage();
+        }
+    }
+}
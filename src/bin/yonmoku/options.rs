@@ -0,0 +1,117 @@
+use std::{str::FromStr, time::Duration};
+
+use yonmoku::{board::Player, search::new_seed};
+
+use crate::{
+    agent::{Agent, AgentKind, Budget, PolicyKind},
+    usage,
+};
+
+/**
+ * サブコマンドに共通のフラグ
+ */
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// 調べるCPU
+    pub agent: Agent,
+    /// watchとmatchの相手
+    pub opponent: Agent,
+    pub budget: Budget,
+    pub threads: Option<usize>,
+    pub seed: Option<u64>,
+    /// agentの手番。playでは人間の手番。
+    pub side: Option<Player>,
+    pub games: usize,
+    /// analyzeで表示する候補手の数
+    pub top: usize,
+    /// 探索の統計を表示する
+    pub stats: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            agent: Agent::new(AgentKind::Puct, PolicyKind::Simple),
+            opponent: Agent::new(AgentKind::Puct, PolicyKind::Simple),
+            budget: Budget::default(),
+            threads: None,
+            seed: None,
+            side: None,
+            games: 100,
+            top: 5,
+            stats: false,
+        }
+    }
+}
+
+/**
+ * 次の引数を値として読む。読めなければusageを表示して終わる
+ */
+pub(crate) fn value<T: FromStr>(args: &mut impl Iterator<Item = String>) -> T {
+    args.next()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| usage())
+}
+
+/**
+ * FromStrの説明つきのエラーを表示する
+ */
+fn named<T: FromStr<Err = String>>(args: &mut impl Iterator<Item = String>) -> T {
+    match args.next().map(|v| v.parse::<T>()) {
+        Some(Ok(v)) => v,
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            usage();
+        }
+        None => usage(),
+    }
+}
+
+impl Options {
+    /**
+     * argが共通のフラグなら値まで読んでtrue
+     */
+    pub fn parse(&mut self, arg: &str, args: &mut impl Iterator<Item = String>) -> bool {
+        match arg {
+            "--agent" => self.agent.kind = named(args),
+            "--policy" => self.agent.policy = named(args),
+            "--opponent" => self.opponent.kind = named(args),
+            "--opponent-policy" => self.opponent.policy = named(args),
+            "--playouts" => self.budget.playouts = value(args),
+            "--growth" => self.budget.growth = value(args),
+            "--time" => self.budget.time = Some(Duration::from_millis(value(args))),
+            "--threads" => self.threads = Some(value(args)),
+            "--seed" => self.seed = Some(value(args)),
+            "--side" => {
+                self.side = match args.next().as_deref() {
+                    Some("black" | "b") => Some(Player::Black),
+                    Some("white" | "w") => Some(Player::White),
+                    _ => usage(),
+                }
+            }
+            "--games" => self.games = value(args),
+            "--top" => self.top = value(args),
+            "--stats" => self.stats = true,
+            _ => return false,
+        }
+        true
+    }
+
+    /**
+     * スレッド数を決め、seedを表示して返す
+     */
+    pub fn start(&self) -> u64 {
+        if let Some(threads) = self.threads {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build_global()
+                .unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                });
+        }
+        let seed = self.seed.unwrap_or_else(new_seed);
+        println!("seed: {}", seed);
+        seed
+    }
+}
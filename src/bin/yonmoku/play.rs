@@ -0,0 +1,327 @@
+use std::io::{self, IsTerminal};
+
+use yonmoku::{
+    arena::{MemoryLimit, WhenFull},
+    bitboard::BitBoard,
+    board::Player,
+    format_column,
+    level::{Level, Style},
+    parse_column,
+    ponder::Ponder,
+    search::{derive_seed, MoveStats, SearchLimits, SearchResult, SearchRng},
+    simple_puct::{McTreeRoot, SimplePolicy},
+};
+
+use crate::{options::Options, prompt, session::Session, tui, usage};
+
+pub type Tree = McTreeRoot<SimplePolicy>;
+
+/**
+ * CPUの強さの設定
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Cpu {
+    pub level: Level,
+    /// --blunderで指定されたらレベルによらずこの確率
+    blunder: Option<f32>,
+    /// --playoutsで指定されたらレベルによらずこの試行回数
+    playouts: Option<usize>,
+    /// 探索木の大きさの上限
+    memory: MemoryLimit,
+    pub seed: u64,
+    /// --seedが指定された。先読みの量は人間の考える時間で変わるので、先読みしない
+    reproducible: bool,
+}
+
+impl Cpu {
+    fn style(&self) -> Style {
+        let mut style = self.level.style();
+        if let Some(blunder) = self.blunder {
+            style.blunder = blunder;
+        }
+        style
+    }
+
+    /**
+     * ply手目の局面から探索する木。seedは手数ごとに変える。
+     */
+    pub fn tree(&self, board: BitBoard, ply: usize) -> Tree {
+        let mut tree = Tree::with_memory(board, self.memory);
+        tree.set_seed(derive_seed(self.seed, ply as u64 + 1));
+        tree
+    }
+
+    fn playouts(&self, stone: usize) -> usize {
+        self.playouts.unwrap_or_else(|| self.level.playouts(stone))
+    }
+
+    /**
+     * 先読みした分に加えて探索する
+     */
+    fn search(&self, tree: &mut Tree, stone: usize) -> SearchResult {
+        tree.search(&SearchLimits::playouts(self.playouts(stone)))
+    }
+
+    /**
+     * CPUの手。強さに応じて崩す。
+     */
+    pub fn think(
+        &self,
+        tree: &mut Tree,
+        stone: usize,
+        rng: &mut SearchRng,
+    ) -> Option<(usize, f32)> {
+        let result = self.search(tree, stone);
+        self.style().choose(&result, rng)
+    }
+
+    /**
+     * 人間へのおすすめ。常に最善手。
+     */
+    pub fn hint(&self, tree: &mut Tree, stone: usize) -> Option<MoveStats> {
+        self.search(tree, stone).best().cloned()
+    }
+
+    /**
+     * stone手目の局面で人間の手番の間に先読みする。多くても一手分の探索まで。
+     */
+    pub fn ponder(&self, tree: Tree, stone: usize) -> Ponder<SimplePolicy> {
+        if self.level.ponder() && !self.reproducible {
+            Ponder::start(tree, self.playouts(stone))
+        } else {
+            Ponder::hold(tree)
+        }
+    }
+}
+
+/**
+ * 人間とCPUの対局
+ */
+pub fn run(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let mut plain = false;
+    let mut cpu = Cpu::default();
+    let mut options = Options::default();
+    let mut guard = false;
+    let mut resume = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--plain" => plain = true,
+            "--level" => match args.next().map(|v| v.parse::<Level>()) {
+                Some(Ok(level)) => cpu.level = level,
+                Some(Err(e)) => {
+                    eprintln!("{}", e);
+                    usage();
+                }
+                None => usage(),
+            },
+            "--blunder" => match args.next().and_then(|v| v.parse::<f32>().ok()) {
+                Some(p) if (0.0..=1.0).contains(&p) => cpu.blunder = Some(p),
+                _ => usage(),
+            },
+            "--playouts" => match args.next().and_then(|v| v.parse::<usize>().ok()) {
+                Some(n) => cpu.playouts = Some(n),
+                None => usage(),
+            },
+            "--guard" => guard = true,
+            "--memory" => match args.next().and_then(|v| v.parse::<usize>().ok()) {
+                Some(mb) => cpu.memory = MemoryLimit::bytes(mb << 20, cpu.memory.when_full),
+                None => usage(),
+            },
+            "--prune" => cpu.memory.when_full = WhenFull::Prune,
+            "--resume" => resume = Some(args.next().unwrap_or_else(|| usage())),
+            "--side" | "--seed" | "--threads" => {
+                options.parse(&arg, &mut args);
+            }
+            _ => usage(),
+        }
+    }
+
+    cpu.reproducible = options.seed.is_some();
+    cpu.seed = options.start();
+
+    let mut session = Session::new(options.side.unwrap_or(Player::Black), cpu);
+    session.guard = guard;
+    let side_chosen = resume.is_some() || options.side.is_some();
+    // 再開する局は先に読んでおく
+    if let Some(path) = resume {
+        if let Err(e) = session.load(&path) {
+            eprintln!("{}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+
+    if !plain && io::stdin().is_terminal() && io::stdout().is_terminal() {
+        tui::run(session, side_chosen)
+    } else {
+        run_plain(session, side_chosen)
+    }
+}
+
+const HELP: &str = "\
+a1..d4, i,j         drop a stone (letter = j from a, number = i + 1)
+undo, M             take back your last move
+redo                replay a move you took back
+hint, C             show the best move for you and why
+level [NAME], L     show or set the CPU level (easy, normal, hard, max)
+guard [on|off]      warn before a move that loses at once (off by default)
+save FILE           save the game
+load FILE           resume a saved game as the side to move
+show                show the board
+help, ?             show this help
+quit, q             quit";
+
+fn show(session: &Session) {
+    crate::show(&session.game);
+}
+
+fn rate_percent(rate: f32) -> i32 {
+    100 - (rate * 100f32) as i32
+}
+
+/**
+ * CPUの手番なら打って盤面を表示する
+ */
+fn cpu_move(session: &mut Session) {
+    if let Some((hand, rate)) = session.cpu_move() {
+        println!(
+            "CPU: {}, CPU Rate: {}%",
+            format_column(hand),
+            rate_percent(rate)
+        );
+        show(session);
+    }
+    if let Some(message) = session.result_message() {
+        println!("{}", message);
+    }
+}
+
+/**
+ * 負けにつながる手なら確かめる。打ってよければtrue。
+ */
+fn confirm(session: &Session, index: usize) -> io::Result<bool> {
+    match session.check_blunder(index) {
+        Some(warning) => {
+            println!("Careful: {}.", warning);
+            let answer = prompt("Play it anyway? [y/N]")?;
+            Ok(answer.is_some_and(|a| a.eq_ignore_ascii_case("y")))
+        }
+        None => Ok(true),
+    }
+}
+
+fn run_plain(mut session: Session, side_chosen: bool) -> io::Result<()> {
+    if !side_chosen {
+        session.human = loop {
+            match prompt("[S]ente or [G]ote?")?.as_deref() {
+                None => return Ok(()),
+                Some("S") => break Player::Black,
+                Some("G") => break Player::White,
+                _ => (),
+            }
+        };
+    }
+    show(&session);
+    cpu_move(&mut session);
+
+    loop {
+        // 入力を待つ間も考えておく
+        session.ponder();
+        let Some(line) = prompt("a1..d4? (help)")? else {
+            return Ok(());
+        };
+        let (command, arg) = line.split_once(' ').unwrap_or((&line, ""));
+        let arg = arg.trim();
+        match command {
+            "" => (),
+            "help" | "?" => println!("{}", HELP),
+            "quit" | "q" => return Ok(()),
+            "show" => show(&session),
+            "undo" | "M" => {
+                if session.undo() {
+                    show(&session);
+                } else {
+                    println!("Nothing to undo.");
+                }
+            }
+            "redo" => {
+                if session.redo() {
+                    show(&session);
+                    if let Some(message) = session.result_message() {
+                        println!("{}", message);
+                    }
+                } else {
+                    println!("Nothing to redo.");
+                }
+            }
+            "hint" | "C" => match session.hint() {
+                Some((m, explanation)) => {
+                    println!(
+                        "Player recommendation: {}, Player Rate: {}%",
+                        format_column(m.hand),
+                        (m.win_rate() * 100f32) as i32
+                    );
+                    println!("{}", explanation);
+                }
+                None => println!("The game is over."),
+            },
+            "level" | "L" => {
+                if !arg.is_empty() {
+                    match arg.parse::<Level>() {
+                        Ok(level) => session.set_level(level),
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                println!("Level: {}", session.cpu.level);
+            }
+            "save" | "load" if arg.is_empty() => println!("{} needs a file name.", command),
+            "save" => match session.save(arg) {
+                Ok(()) => println!("Saved to {}.", arg),
+                Err(e) => println!("{}: {}", arg, e),
+            },
+            "load" => match session.load(arg) {
+                Ok(()) => {
+                    show(&session);
+                    match session.result_message() {
+                        Some(message) => println!("{}", message),
+                        None => println!(
+                            "Resumed {} moves. You play the side to move.",
+                            session.game.ply()
+                        ),
+                    }
+                }
+                Err(e) => println!("{}: {}", arg, e),
+            },
+            "guard" => {
+                match arg {
+                    "on" => session.guard = true,
+                    "off" => session.guard = false,
+                    "" => (),
+                    _ => println!("guard takes on or off."),
+                }
+                println!(
+                    "Blunder guard: {}",
+                    if session.guard { "on" } else { "off" }
+                );
+            }
+            _ => match parse_column(&line) {
+                Some(index) => {
+                    if !confirm(&session, index)? {
+                        continue;
+                    }
+                    match session.play(index) {
+                        Ok(()) => {
+                            show(&session);
+                            if let Some(message) = session.result_message() {
+                                println!("{}", message);
+                            } else {
+                                cpu_move(&mut session);
+                            }
+                        }
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                None => println!("Unknown command: {}. Type help for the list.", line),
+            },
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::Instant;
+
+use yonmoku::{
+    bitboard::BitBoard,
+    dfpn::prove_with_table,
+    format_column,
+    game::Game,
+    proof::{verify, ProofTree},
+    search::Proof,
+};
+
+use crate::{options::value, usage};
+
+/**
+ * argが局面を決めるフラグなら値まで読んでboardに入れ、true
+ */
+pub fn parse_position(
+    arg: &str,
+    args: &mut impl Iterator<Item = String>,
+    board: &mut BitBoard,
+) -> bool {
+    match arg {
+        "--moves" => {
+            let record = args.next().unwrap_or_else(|| usage());
+            match Game::from_record(&record.replace(',', " ")) {
+                Ok(game) => *board = game.board().clone(),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        "--position" => {
+            let position = args.next().unwrap_or_else(|| usage());
+            *board = BitBoard::from_position_string(&position).unwrap_or_else(|| {
+                eprintln!("invalid position: {}", position);
+                std::process::exit(1);
+            });
+        }
+        _ => return false,
+    }
+    true
+}
+
+/**
+ * 手番側から見た結果と、勝つ手か引き分けにする手
+ */
+pub fn verdict(proof: Option<Proof>, best: Option<usize>) -> String {
+    match (proof, best) {
+        (Some(Proof::Win), Some(best)) => format!("win by {}", format_column(best)),
+        (Some(Proof::Draw), Some(best)) => format!("draw, holds with {}", format_column(best)),
+        (Some(Proof::Win), None) => "win".to_owned(),
+        (Some(Proof::Draw), None) => "draw".to_owned(),
+        (Some(Proof::Lose), _) => "loss".to_owned(),
+        (None, _) => "unknown".to_owned(),
+    }
+}
+
+/**
+ * 証明木を書き出す。勝ち負けが決まっていなければ書かない。
+ */
+pub fn export(tree: Option<ProofTree>, path: &str) {
+    let Some(tree) = tree else {
+        eprintln!("no forced win to export");
+        std::process::exit(1);
+    };
+    if let Err(e) = tree.save(path) {
+        eprintln!("{}: {}", path, e);
+        std::process::exit(1);
+    }
+    println!("proof tree: {} ({} moves)", path, tree.len());
+}
+
+/**
+ * 局面をdf-pnで読み切る。ノード数の上限までに決まらなければunknown。
+ */
+pub fn run(mut args: impl Iterator<Item = String>) {
+    let mut board = BitBoard::new();
+    let mut nodes = 1_000_000;
+    let mut export_path = None;
+    while let Some(arg) = args.next() {
+        if parse_position(&arg, &mut args, &mut board) {
+            continue;
+        }
+        match arg.as_str() {
+            "--nodes" => nodes = value(&mut args),
+            "--export" => export_path = Some(args.next().unwrap_or_else(|| usage())),
+            _ => usage(),
+        }
+    }
+
+    println!("position: {}", board.position_string());
+    let start = Instant::now();
+    let (result, table) = prove_with_table(&board, nodes);
+    println!(
+        "{:?} to move: {} (nodes {}, {:.2}s)",
+        board.next_player(),
+        verdict(result.proof, result.best),
+        result.nodes,
+        start.elapsed().as_secs_f64()
+    );
+
+    if let Some(path) = export_path {
+        // 読み切った表から作る
+        export(table.and_then(|mut table| table.proof_tree(&board)), &path);
+    }
+}
+
+/**
+ * 証明木を、探索を使わずに打ち直して確かめる
+ */
+pub fn run_verify(mut args: impl Iterator<Item = String>) {
+    let path = args.next().unwrap_or_else(|| usage());
+    let tree = ProofTree::load(&path).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        std::process::exit(1);
+    });
+    println!("position: {}", tree.root.position_string());
+    match verify(&tree) {
+        Ok(lines) => println!(
+            "ok: {:?} wins ({} moves, {} completed lines)",
+            tree.winner,
+            tree.len(),
+            lines
+        ),
+        Err(e) => {
+            println!("invalid: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
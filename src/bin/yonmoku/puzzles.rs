@@ -0,0 +1,86 @@
+use std::{collections::HashSet, fs, io};
+
+use yonmoku::{
+    bitboard::BitBoard,
+    puzzle::MateSearch,
+    search::{derive_seed, SearchStats},
+};
+
+use crate::{
+    agent::Budget,
+    battle::play,
+    options::{value, Options},
+    usage,
+};
+
+/**
+ * 自己対局の局面から、ちょうどk手で勝ち、勝つ一手目がひとつだけの局面を集める
+ */
+pub fn run(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    // selfplayと同じく、多くの局を打つので既定の探索量は少なめ
+    let mut options = Options {
+        budget: Budget {
+            playouts: 5_000,
+            growth: 16,
+            time: None,
+        },
+        ..Default::default()
+    };
+    let mut k = 3;
+    let mut out = None;
+    while let Some(arg) = args.next() {
+        if options.parse(&arg, &mut args) {
+            continue;
+        }
+        match arg.as_str() {
+            "--win-in" => k = value(&mut args),
+            "--out" => out = Some(value::<String>(&mut args)),
+            _ => usage(),
+        }
+    }
+    if k == 0 {
+        usage();
+    }
+    let seed = options.start();
+
+    let mut lines = vec![format!(
+        "# win in {}, {} self-play games by {}, seed {}",
+        k, options.games, options.agent, seed
+    )];
+    let mut seen = HashSet::new();
+    let mut stats = [SearchStats::default(); 2];
+    for i in 0..options.games {
+        let game = play(
+            options.agent,
+            options.agent,
+            &options.budget,
+            derive_seed(seed, i as u64),
+            &mut stats,
+        );
+        // 局ごとに表を捨てて、メモリが増え続けないようにする
+        let mut search = MateSearch::new();
+        let mut board = BitBoard::new();
+        for &hand in game.moves() {
+            if seen.insert(board.canonical()) {
+                if let Some(puzzle) = search.puzzle(&board, k) {
+                    let line = puzzle.to_line();
+                    println!("{}", line);
+                    lines.push(line);
+                }
+            }
+            board = board.put(hand).unwrap();
+        }
+        eprintln!(
+            "game {}/{}: {} puzzles",
+            i + 1,
+            options.games,
+            lines.len() - 1
+        );
+    }
+
+    if let Some(path) = out {
+        fs::write(&path, lines.join("\n") + "\n")?;
+        eprintln!("wrote {} puzzles to {}", lines.len() - 1, path);
+    }
+    Ok(())
+}
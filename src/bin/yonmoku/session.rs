@@ -0,0 +1,217 @@
+use std::{io, path::Path};
+
+use yonmoku::{
+    board::Player,
+    explain::{blunder, explain, Explanation, Warning},
+    game::{Game, GameResult},
+    level::Level,
+    ponder::Ponder,
+    search::{rng_stream, MoveStats, Proof, SearchLimits, SearchRng},
+    simple_puct::SimplePolicy,
+};
+
+use crate::play::{Cpu, Tree};
+
+/// 負けを探す軽い探索の、候補手あたりの試行回数
+const GUARD_PLAYOUTS: usize = 2_000;
+
+/**
+ * 人間とCPUの一局。画面の違いによらない部分。
+ */
+pub struct Session {
+    pub game: Game,
+    pub human: Player,
+    pub cpu: Cpu,
+    /// 人間の手が負けにつながるなら警告する
+    pub guard: bool,
+    /// 今の局面の木。人間の手番の間はponderが持っている。
+    tree: Option<Tree>,
+    ponder: Option<Ponder<SimplePolicy>>,
+    /// 手を崩すための乱数
+    rng: SearchRng,
+}
+
+impl Session {
+    pub fn new(human: Player, cpu: Cpu) -> Self {
+        Session {
+            game: Game::new(),
+            human,
+            cpu,
+            guard: false,
+            tree: None,
+            ponder: None,
+            rng: rng_stream(cpu.seed, 0),
+        }
+    }
+
+    fn take_tree(&mut self) -> Tree {
+        match self.ponder.take() {
+            Some(ponder) => ponder.stop(),
+            None => self
+                .tree
+                .take()
+                .unwrap_or_else(|| self.cpu.tree(self.game.board().clone(), self.game.ply())),
+        }
+    }
+
+    /**
+     * 局面が飛んだら先読みは捨てる
+     */
+    fn reset_tree(&mut self) {
+        self.ponder = None;
+        self.tree = None;
+    }
+
+    pub fn is_human_turn(&self) -> bool {
+        !self.game.is_over() && self.game.side_to_move() == self.human
+    }
+
+    /**
+     * 人間の手番なら裏で考えはじめる
+     */
+    pub fn ponder(&mut self) {
+        if self.ponder.is_none() && self.is_human_turn() {
+            let tree = self.take_tree();
+            self.ponder = Some(self.cpu.ponder(tree, self.game.ply()));
+        }
+    }
+
+    fn push(&mut self, index: usize) -> Result<(), String> {
+        let board = self
+            .game
+            .play(index)
+            .map_err(|e| format!("Illegal move: {}.", e))?
+            .clone();
+        let tree = self.take_tree();
+        let ply = self.game.ply();
+        self.tree = Some(
+            tree.play(index)
+                .unwrap_or_else(|| self.cpu.tree(board, ply)),
+        );
+        Ok(())
+    }
+
+    /**
+     * guardが有効なとき、人間の手がすぐ負けにつながるか調べる。
+     * 一手で勝たれる手を先に見て、なければ軽く探索して読み切れた負けを探す。
+     */
+    pub fn check_blunder(&self, index: usize) -> Option<Warning> {
+        if !self.guard || !self.is_human_turn() {
+            return None;
+        }
+        let board = self.game.board();
+        if let Some(warning) = blunder(board, index) {
+            return Some(warning);
+        }
+        let after = board.try_play(index).ok()?;
+        if after.is_won() || after.is_full() {
+            return None;
+        }
+        let result = self
+            .cpu
+            .tree(after, self.game.ply() + 1)
+            .search(&SearchLimits::playouts(GUARD_PLAYOUTS));
+        let best = result.best()?;
+        (best.proven == Some(Proof::Win)).then(|| Warning::ForcedLoss(best.pv.clone()))
+    }
+
+    pub fn play(&mut self, index: usize) -> Result<(), String> {
+        if !self.game.is_over() && !self.is_human_turn() {
+            return Err("It is not your turn.".to_owned());
+        }
+        self.push(index)
+    }
+
+    /**
+     * CPUの手番なら打つ。return (hand, eval)
+     */
+    pub fn cpu_move(&mut self) -> Option<(usize, f32)> {
+        if self.game.is_over() || self.is_human_turn() {
+            return None;
+        }
+        let mut tree = self.take_tree();
+        let hand = self.cpu.think(&mut tree, self.game.ply(), &mut self.rng);
+        self.tree = Some(tree);
+        let (index, _) = hand?;
+        self.push(index).ok()?;
+        hand
+    }
+
+    /**
+     * 人間へのおすすめの手と、その理由
+     */
+    pub fn hint(&mut self) -> Option<(MoveStats, Explanation)> {
+        if self.game.is_over() {
+            return None;
+        }
+        let mut tree = self.take_tree();
+        let m = self.cpu.hint(&mut tree, self.game.ply());
+        self.tree = Some(tree);
+        let m = m?;
+        let explanation = explain(self.game.board(), m.hand, &m.pv)?;
+        Some((m, explanation))
+    }
+
+    /**
+     * 人間の最後の手まで戻す
+     */
+    pub fn undo(&mut self) -> bool {
+        let first_human = if self.human == Player::Black { 0 } else { 1 };
+        if self.game.ply() <= first_human {
+            return false;
+        }
+        self.reset_tree();
+        while self.game.ply() > first_human {
+            self.game.undo();
+            if self.game.side_to_move() == self.human {
+                break;
+            }
+        }
+        true
+    }
+
+    /**
+     * 待ったした手を次の人間の手番まで戻す
+     */
+    pub fn redo(&mut self) -> bool {
+        if !self.game.can_redo() {
+            return false;
+        }
+        self.reset_tree();
+        while self.game.redo().is_some() && !self.is_human_turn() && !self.game.is_over() {}
+        true
+    }
+
+    pub fn set_level(&mut self, level: Level) {
+        self.cpu.level = level;
+        // 先読みするかどうかが変わるので預け直す
+        if let Some(ponder) = self.ponder.take() {
+            self.tree = Some(ponder.stop());
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.game.save(path)
+    }
+
+    /**
+     * 保存した局を再開する。手番側が人間になる。
+     */
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let game = Game::load(path)?;
+        self.reset_tree();
+        if !game.is_over() {
+            self.human = game.side_to_move();
+        }
+        self.game = game;
+        Ok(())
+    }
+
+    pub fn result_message(&self) -> Option<String> {
+        match self.game.result()? {
+            GameResult::Win(player) if player == self.human => Some("You win!".to_owned()),
+            GameResult::Win(_) => Some("You lose.".to_owned()),
+            GameResult::Draw => Some("Draw.".to_owned()),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use yonmoku::{
+    bitboard::BitBoard,
+    dfpn::ENTRY_BYTES,
+    format_column,
+    search::Proof,
+    solver::{MoveStatus, Solver},
+};
+
+use crate::{
+    options::value,
+    prove::{export, parse_position, verdict},
+    usage,
+};
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn status_text(status: &MoveStatus) -> &'static str {
+    match (status.proof(), status.wins, status.loses) {
+        (Some(Proof::Win), _, _) => "win",
+        (Some(Proof::Lose), _, _) => "loss",
+        (Some(Proof::Draw), _, _) => "draw",
+        (None, Some(false), _) => "no win",
+        (None, _, Some(false)) => "no loss",
+        (None, _, _) => "-",
+    }
+}
+
+fn print_moves(moves: &[MoveStatus]) {
+    let line = moves
+        .iter()
+        .map(|m| format!("{} {}", format_column(m.hand), status_text(m)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!("moves: {}", line);
+}
+
+/**
+ * 書きかけで止まっても前のチェックポイントが残るよう、別のファイルに書いてから置き換える
+ */
+fn save(solver: &Solver, path: &Path) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut w = BufWriter::new(File::create(&tmp)?);
+    solver.save(&mut w)?;
+    w.flush()?;
+    drop(w);
+    fs::rename(tmp, path)
+}
+
+/**
+ * 局面を何日もかけて読み切る。チェックポイントがあればその続きから調べる。
+ */
+pub fn run(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let mut board = None;
+    let mut checkpoint = "yonmoku.solve".to_owned();
+    let mut interval = 600;
+    let mut slice = 1_000_000;
+    let mut memory = 4096;
+    let mut nodes = u64::MAX;
+    let mut export_path = None;
+    while let Some(arg) = args.next() {
+        let mut position = BitBoard::new();
+        if parse_position(&arg, &mut args, &mut position) {
+            board = Some(position);
+            continue;
+        }
+        match arg.as_str() {
+            "--checkpoint" => checkpoint = value(&mut args),
+            "--interval" => interval = value(&mut args),
+            "--slice" => slice = value(&mut args),
+            "--memory" => memory = value(&mut args),
+            "--nodes" => nodes = value(&mut args),
+            "--export" => export_path = Some(value::<String>(&mut args)),
+            _ => usage(),
+        }
+    }
+    let path = Path::new(&checkpoint);
+    let interval = Duration::from_secs(interval);
+    let max_entries = memory * 1024 * 1024 / ENTRY_BYTES;
+
+    let mut solver = if path.exists() {
+        let solver = Solver::load(&mut BufReader::new(File::open(path)?))?;
+        if board.as_ref().is_some_and(|board| board != solver.root()) {
+            eprintln!(
+                "{} is for position {}",
+                checkpoint,
+                solver.root().position_string()
+            );
+            std::process::exit(1);
+        }
+        println!(
+            "resumed from {}: nodes {}, elapsed {}",
+            checkpoint,
+            solver.nodes(),
+            format_duration(solver.elapsed())
+        );
+        solver
+    } else {
+        Solver::new(&board.unwrap_or_else(BitBoard::new))
+    };
+    println!("position: {}", solver.root().position_string());
+
+    let start = Instant::now();
+    let start_nodes = solver.nodes();
+    let mut saved = Instant::now();
+    let mut moves = solver.moves();
+    print_moves(&moves);
+    loop {
+        let result = solver.step(slice);
+        solver.shrink(max_entries);
+        let searched = solver.nodes() - start_nodes;
+        println!(
+            "[{}] nodes {} ({:.0}/s) table {} phase {}",
+            format_duration(solver.elapsed()),
+            solver.nodes(),
+            searched as f64 / start.elapsed().as_secs_f64(),
+            solver.table_len(),
+            if solver.second_phase() { 2 } else { 1 }
+        );
+        let now = solver.moves();
+        if now != moves {
+            moves = now;
+            print_moves(&moves);
+        }
+
+        let done = result.is_some() || searched >= nodes;
+        if done || saved.elapsed() >= interval {
+            save(&solver, path)?;
+            saved = Instant::now();
+            println!("checkpoint: {}", checkpoint);
+        }
+        if done {
+            break;
+        }
+    }
+    println!(
+        "{:?} to move: {}",
+        solver.root().next_player(),
+        verdict(solver.result(), solver.best())
+    );
+    if let Some(path) = export_path {
+        if solver.result().is_some() {
+            export(solver.proof_tree(), &path);
+        }
+    }
+    Ok(())
+}
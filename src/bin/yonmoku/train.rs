@@ -0,0 +1,154 @@
+use std::{fs, io};
+
+use rand::seq::SliceRandom;
+use yonmoku::{
+    bitboard::BitBoard,
+    explain::blunder,
+    format_column, parse_column,
+    puzzle::{parse_puzzles, MateSearch, Puzzle},
+    search::{new_seed, rng_stream},
+};
+
+use crate::{options::value, prompt, usage};
+
+/**
+ * セッションの成績。一度も間違えずに解いた問題を数える。
+ */
+#[derive(Clone, Copy, Debug, Default)]
+struct Score {
+    solved: usize,
+    played: usize,
+    streak: usize,
+    best_streak: usize,
+}
+
+impl Score {
+    fn record(&mut self, clean: bool) {
+        self.played += 1;
+        if clean {
+            self.solved += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.streak = 0;
+        }
+    }
+}
+
+fn show(board: &BitBoard, last: Option<usize>) {
+    println!("{}", board.render().last(last).winning_line().auto_color());
+}
+
+/**
+ * 勝ちにならない手の短い説明
+ */
+fn why_wrong(search: &mut MateSearch, board: &BitBoard, hand: usize, k: usize) -> String {
+    if let Some(warning) = blunder(board, hand) {
+        return warning.to_string();
+    }
+    if k == 1 {
+        return "this does not complete a line".to_owned();
+    }
+    match search.refutation(board, hand, k) {
+        Some(reply) => format!(
+            "{} holds: there is no win in {} more moves",
+            format_column(reply),
+            k - 1
+        ),
+        None => "this lets the game end in a draw".to_owned(),
+    }
+}
+
+/**
+ * 一問解かせる。return 間違えずに解けたか。入力が終わればNone
+ */
+fn solve(search: &mut MateSearch, puzzle: &Puzzle) -> io::Result<Option<bool>> {
+    let mut board = puzzle.board.clone();
+    let mut last = None;
+    let mut clean = true;
+    let mut k = puzzle.k;
+    while k > 0 {
+        show(&board, last);
+        println!("{:?} to move: win in {}", board.next_player(), k);
+        let Some(line) = prompt("a1..d4? [S]kip")? else {
+            return Ok(None);
+        };
+        if line == "S" {
+            let solution = puzzle.solution.iter().map(|&h| format_column(h));
+            println!("solution: {}", solution.collect::<Vec<_>>().join(" "));
+            return Ok(Some(false));
+        }
+        let Some(hand) = parse_column(&line) else {
+            continue;
+        };
+        if let Err(e) = board.try_play(hand) {
+            println!("{}", e);
+            continue;
+        }
+        if !search.keeps_win(&board, hand, k) {
+            println!("Not quite: {}.", why_wrong(search, &board, hand, k));
+            clean = false;
+            continue;
+        }
+
+        board = board.put(hand).unwrap();
+        last = Some(hand);
+        if board.is_won() {
+            break;
+        }
+        k -= 1;
+        let reply = search.defence(&board, k).unwrap();
+        println!("CPU: {}", format_column(reply));
+        board = board.put(reply).unwrap();
+        last = Some(reply);
+    }
+    show(&board, last);
+    println!("Solved!");
+    Ok(Some(clean))
+}
+
+/**
+ * 問題のファイルを順に出し、勝ち切る手順を入力させる
+ */
+pub fn run(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let mut path = None;
+    let mut shuffle = false;
+    let mut seed = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--shuffle" => shuffle = true,
+            "--seed" => seed = Some(value(&mut args)),
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg),
+            _ => usage(),
+        }
+    }
+    let path = path.unwrap_or_else(|| usage());
+    let mut puzzles = parse_puzzles(&fs::read_to_string(&path)?).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        std::process::exit(1);
+    });
+    if shuffle {
+        let seed = seed.unwrap_or_else(new_seed);
+        println!("seed: {}", seed);
+        puzzles.shuffle(&mut rng_stream(seed, 0));
+    }
+
+    let mut search = MateSearch::new();
+    let mut score = Score::default();
+    for (i, puzzle) in puzzles.iter().enumerate() {
+        println!("puzzle {}/{}", i + 1, puzzles.len());
+        let Some(clean) = solve(&mut search, puzzle)? else {
+            break;
+        };
+        score.record(clean);
+        println!(
+            "score {}/{}, streak {} (best {})",
+            score.solved, score.played, score.streak, score.best_streak
+        );
+    }
+    println!(
+        "session: solved {} of {} cleanly, best streak {}",
+        score.solved, score.played, score.best_streak
+    );
+    Ok(())
+}
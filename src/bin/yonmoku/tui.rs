@@ -0,0 +1,366 @@
+use std::io::{self, Write};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{
+        Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+    },
+    terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use yonmoku::{bitboard::BitBoard, board::Piece, board::Player, format_column, N};
+
+use crate::session::Session;
+
+const LAYER_WIDTH: u16 = 3 * N as u16 + 3;
+const BOARD_X: u16 = 3;
+const BOARD_Y: u16 = 2;
+
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct App {
+    session: Session,
+    cursor: (usize, usize),
+    status: String,
+    /// ヒントの説明。カーソル移動以外の操作で消す。
+    explanation: Vec<String>,
+    /// 警告を出した手。もう一度Enterで打つ。
+    pending: Option<usize>,
+}
+
+fn player_color(player: Player) -> Color {
+    match player {
+        Player::Black => Color::Red,
+        Player::White => Color::Blue,
+    }
+}
+
+fn player_name(player: Player) -> &'static str {
+    match player {
+        Player::Black => "black",
+        Player::White => "white",
+    }
+}
+
+impl App {
+    fn board(&self) -> &BitBoard {
+        self.session.game.board()
+    }
+
+    fn cpu_move(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.session.game.is_over() || self.session.is_human_turn() {
+            return Ok(());
+        }
+        self.status = "CPU thinking...".to_owned();
+        self.render(out)?;
+        if let Some((hand, rate)) = self.session.cpu_move() {
+            self.status = format!(
+                "CPU: {}, CPU Rate: {}%",
+                format_column(hand),
+                100 - (rate * 100f32) as i32
+            );
+        }
+        if let Some(message) = self.session.result_message() {
+            self.status = format!("{} {}", self.status, message);
+        }
+        Ok(())
+    }
+
+    fn hint(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.session.game.is_over() {
+            return Ok(());
+        }
+        self.status = "thinking...".to_owned();
+        self.render(out)?;
+        if let Some((m, explanation)) = self.session.hint() {
+            self.cursor = (m.hand / N, m.hand % N);
+            self.status = format!(
+                "Player recommendation: {}, Player Rate: {}%",
+                format_column(m.hand),
+                (m.win_rate() * 100f32) as i32
+            );
+            self.explanation = explanation.to_string().lines().map(str::to_owned).collect();
+        }
+        Ok(())
+    }
+
+    fn change_level(&mut self) {
+        self.session.set_level(self.session.cpu.level.next());
+        self.status = format!("Level: {}", self.session.cpu.level);
+    }
+
+    fn render(&self, out: &mut impl Write) -> io::Result<()> {
+        let board = self.board();
+        let human = self.session.human;
+        let last = self
+            .session
+            .game
+            .last_move()
+            .map(|m| (m, board.height(m) - 1));
+        let threats = [Player::Black, Player::White].map(|player| (player, board.threats(player)));
+        let line = self.session.game.winning_line().unwrap_or_default();
+
+        queue!(out, terminal::Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+        queue!(
+            out,
+            Print("Yonmoku  You: "),
+            SetForegroundColor(player_color(human)),
+            Print(format!("● {}", player_name(human))),
+            ResetColor,
+            Print("  CPU: "),
+            SetForegroundColor(player_color(human.next_player())),
+            Print(format!("● {}", player_name(human.next_player()))),
+            ResetColor,
+            Print(format!("  Level: {}", self.session.cpu.level)),
+        )?;
+
+        for k in 0..N {
+            let x = BOARD_X + k as u16 * LAYER_WIDTH;
+            queue!(
+                out,
+                cursor::MoveTo(x, BOARD_Y),
+                Print(format!("layer {}", k + 1)),
+                cursor::MoveTo(x, BOARD_Y + 1),
+                Print(
+                    (0..N)
+                        .map(|j| format!(" {} ", (b'a' + j as u8) as char))
+                        .collect::<String>()
+                ),
+            )?;
+            for i in 0..N {
+                let y = BOARD_Y + 2 + i as u16;
+                if k == 0 {
+                    queue!(out, cursor::MoveTo(0, y), Print(i + 1))?;
+                }
+                queue!(out, cursor::MoveTo(x, y))?;
+                for j in 0..N {
+                    let index_2d = i * N + j;
+                    if self.cursor == (i, j) {
+                        queue!(out, SetBackgroundColor(Color::DarkGrey))?;
+                    }
+                    if line.contains(&(index_2d, k)) {
+                        queue!(out, SetBackgroundColor(Color::Yellow))?;
+                    }
+                    if last == Some((index_2d, k)) {
+                        queue!(out, SetAttribute(Attribute::Reverse))?;
+                    }
+                    match board.piece(index_2d, k) {
+                        Piece::Black => queue!(
+                            out,
+                            SetForegroundColor(player_color(Player::Black)),
+                            Print(" ● ")
+                        )?,
+                        Piece::White => queue!(
+                            out,
+                            SetForegroundColor(player_color(Player::White)),
+                            Print(" ● ")
+                        )?,
+                        Piece::Empty => {
+                            // 置けばラインが完成するマス
+                            match threats.iter().find(|(_, t)| t.contains(&(index_2d, k))) {
+                                Some((player, _)) => {
+                                    if board.height(index_2d) == k {
+                                        queue!(out, SetAttribute(Attribute::Bold))?;
+                                    }
+                                    queue!(
+                                        out,
+                                        SetForegroundColor(player_color(*player)),
+                                        Print(" × ")
+                                    )?
+                                }
+                                None => queue!(out, Print(" · "))?,
+                            }
+                        }
+                    }
+                    queue!(out, SetAttribute(Attribute::Reset), ResetColor)?;
+                }
+            }
+        }
+
+        // 棋譜
+        let panel_x = BOARD_X + N as u16 * LAYER_WIDTH + 2;
+        queue!(out, cursor::MoveTo(panel_x, BOARD_Y), Print("Moves"))?;
+        let lines = self
+            .session
+            .game
+            .moves()
+            .chunks(2)
+            .enumerate()
+            .map(|(n, pair)| {
+                let pair = pair.iter().map(|&m| format_column(m)).collect::<Vec<_>>();
+                format!("{:>2}. {}", n + 1, pair.join("  "))
+            })
+            .collect::<Vec<_>>();
+        let height = terminal::size()?.1.saturating_sub(BOARD_Y + 4) as usize;
+        for (row, line) in lines[lines.len().saturating_sub(height)..]
+            .iter()
+            .enumerate()
+        {
+            queue!(
+                out,
+                cursor::MoveTo(panel_x, BOARD_Y + 1 + row as u16),
+                Print(line)
+            )?;
+        }
+
+        let status_y = BOARD_Y + 3 + N as u16;
+        queue!(out, cursor::MoveTo(0, status_y), Print(&self.status),)?;
+        for (row, line) in self.explanation.iter().enumerate() {
+            queue!(
+                out,
+                cursor::MoveTo(2, status_y + 1 + row as u16),
+                Print(line)
+            )?;
+        }
+        queue!(
+            out,
+            cursor::MoveTo(0, status_y + 2 + self.explanation.len() as u16),
+            Print("←↑↓→ select  Enter drop  c hint  u undo  r redo  l level  b guard  q quit  × threat"),
+        )?;
+        out.flush()
+    }
+}
+
+fn read_key() -> io::Result<KeyEvent> {
+    loop {
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                return Ok(key);
+            }
+        }
+    }
+}
+
+/**
+ * 終わるキー。raw modeではCtrl-Cもキーとして届く。
+ */
+fn is_quit(key: &KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+        || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+}
+
+pub fn run(session: Session, side_chosen: bool) -> io::Result<()> {
+    let _guard = TerminalGuard::new()?;
+    let mut out = io::stdout();
+    let mut app = App {
+        session,
+        cursor: (0, 0),
+        status: "[S]ente or [G]ote? ([L]evel)".to_owned(),
+        explanation: vec![],
+        pending: None,
+    };
+
+    if !side_chosen {
+        app.render(&mut out)?;
+        loop {
+            let key = read_key()?;
+            if is_quit(&key) {
+                return Ok(());
+            }
+            match key.code {
+                KeyCode::Char('s' | 'S') => break,
+                KeyCode::Char('l' | 'L') => {
+                    app.change_level();
+                    app.status = "[S]ente or [G]ote? ([L]evel)".to_owned();
+                    app.render(&mut out)?;
+                }
+                KeyCode::Char('g' | 'G') => {
+                    app.session.human = Player::White;
+                    break;
+                }
+                _ => (),
+            }
+        }
+    }
+    app.status = app
+        .session
+        .result_message()
+        .unwrap_or_else(|| "Your turn.".to_owned());
+    app.cpu_move(&mut out)?;
+
+    loop {
+        app.session.ponder();
+        app.render(&mut out)?;
+        let key = read_key()?;
+        if is_quit(&key) {
+            return Ok(());
+        }
+        let key = key.code;
+        if !matches!(
+            key,
+            KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right
+        ) {
+            app.explanation.clear();
+        }
+        if !matches!(key, KeyCode::Enter | KeyCode::Char(' ')) {
+            app.pending = None;
+        }
+        match key {
+            KeyCode::Up => app.cursor.0 = app.cursor.0.saturating_sub(1),
+            KeyCode::Down => app.cursor.0 = (app.cursor.0 + 1).min(N - 1),
+            KeyCode::Left => app.cursor.1 = app.cursor.1.saturating_sub(1),
+            KeyCode::Right => app.cursor.1 = (app.cursor.1 + 1).min(N - 1),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let index = app.cursor.0 * N + app.cursor.1;
+                if app.pending.take() != Some(index) {
+                    if let Some(warning) = app.session.check_blunder(index) {
+                        app.status = format!("Careful: {}. Enter again to play it.", warning);
+                        app.pending = Some(index);
+                        continue;
+                    }
+                }
+                if let Err(e) = app.session.play(index) {
+                    app.status = e;
+                    continue;
+                }
+                if let Some(message) = app.session.result_message() {
+                    app.status = message;
+                    continue;
+                }
+                app.cpu_move(&mut out)?;
+            }
+            KeyCode::Char('c') => app.hint(&mut out)?,
+            KeyCode::Char('u') => {
+                app.status = if app.session.undo() {
+                    "Matta.".to_owned()
+                } else {
+                    "Nothing to undo.".to_owned()
+                };
+            }
+            KeyCode::Char('r') => {
+                app.status = if app.session.redo() {
+                    app.session
+                        .result_message()
+                        .unwrap_or_else(|| "Redo.".to_owned())
+                } else {
+                    "Nothing to redo.".to_owned()
+                };
+            }
+            KeyCode::Char('l') => app.change_level(),
+            KeyCode::Char('b') => {
+                app.session.guard = !app.session.guard;
+                app.status = format!(
+                    "Blunder guard: {}",
+                    if app.session.guard { "on" } else { "off" }
+                );
+            }
+            _ => (),
+        }
+    }
+}
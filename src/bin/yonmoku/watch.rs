@@ -0,0 +1,65 @@
+use yonmoku::{
+    board::Player,
+    format_column,
+    game::{Game, GameResult},
+};
+
+use crate::{agent::AgentKind, options::Options, show, usage};
+
+/**
+ * CPU同士の一局を表示する。agentは--sideの手番(既定は先手)を持つ。
+ */
+pub fn run(mut args: impl Iterator<Item = String>) {
+    let mut options = Options::default();
+    options.agent.kind = AgentKind::Ucb1;
+    options.opponent.kind = AgentKind::Ucb1;
+    while let Some(arg) = args.next() {
+        if !options.parse(&arg, &mut args) {
+            usage();
+        }
+    }
+    let seed = options.start();
+    let side = options.side.unwrap_or(Player::Black);
+
+    let mut game = Game::new();
+    while !game.is_over() {
+        let stone = game.ply() + 1;
+        let agent = if game.side_to_move() == side {
+            options.agent
+        } else {
+            options.opponent
+        };
+        let choice = agent
+            .choose(&game, &options.budget, seed)
+            .expect("no legal move");
+        let hand = choice.hand;
+        let name = format!("{:?} ({})", game.side_to_move(), agent);
+        match choice.rate {
+            Some(rate) => {
+                // rateは手番側から見た勝率
+                let sente_rate = match game.side_to_move() {
+                    Player::Black => rate,
+                    Player::White => 1f32 - rate,
+                };
+                println!(
+                    "{}: {}: {}, Rate for Sente: {:.1}%",
+                    stone,
+                    name,
+                    format_column(hand),
+                    sente_rate * 100f32
+                );
+            }
+            None => println!("{}: {}: {}", stone, name, format_column(hand)),
+        }
+        if options.stats && choice.rate.is_some() {
+            println!("{}", choice.stats);
+        }
+        game.play(hand).unwrap();
+        show(&game);
+    }
+
+    match game.result() {
+        Some(GameResult::Win(player)) => println!("{:?} wins", player),
+        _ => println!("draw"),
+    }
+}
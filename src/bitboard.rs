@@ -1,7 +1,7 @@
 use lazy_static::lazy_static;
 
 use crate::{
-    board::{self, ArrayBoard, Player},
+    board::{self, ArrayBoard, MoveError, Player},
     N,
 };
 
@@ -35,77 +35,77 @@ lazy_static! {
 
             // 4 in z
             if k == N - 1 {
-                masks.push((0..N-1).map(|kp| index_from_ijk(i, j, kp)).map(|b| index_to_bit(b)).sum());
+                masks.push((0..N-1).map(|kp| index_from_ijk(i, j, kp)).map(index_to_bit).sum());
             }
 
             // i
             masks.push(
-                (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, j, k)).map(|b| index_to_bit(b)).sum()
+                (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, j, k)).map(index_to_bit).sum()
             );
 
             // j
             masks.push(
-                (0..N).filter(|&jp| jp != j).map(|jp| index_from_ijk(i, jp, k)).map(|b| index_to_bit(b)).sum()
+                (0..N).filter(|&jp| jp != j).map(|jp| index_from_ijk(i, jp, k)).map(index_to_bit).sum()
             );
 
             if i == j {
                 masks.push(
-                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, ip, k)).map(|b| index_to_bit(b)).sum()
+                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, ip, k)).map(index_to_bit).sum()
                 );
             }
 
             if i == N - 1 - j {
                 masks.push(
-                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, N - 1 - ip, k)).map(|b| index_to_bit(b)).sum()
+                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, N - 1 - ip, k)).map(index_to_bit).sum()
                 );
             }
 
             if i == k {
                 masks.push(
-                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, j, ip)).map(|b| index_to_bit(b)).sum()
+                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, j, ip)).map(index_to_bit).sum()
                 );
             }
 
             if i == N - 1 - k {
                 masks.push(
-                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, j, N - 1 - ip)).map(|b| index_to_bit(b)).sum()
+                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, j, N - 1 - ip)).map(index_to_bit).sum()
                 );
             }
 
             if j == k {
                 masks.push(
-                    (0..N).filter(|&jp| jp != j).map(|jp| index_from_ijk(i, jp, jp)).map(|b| index_to_bit(b)).sum()
+                    (0..N).filter(|&jp| jp != j).map(|jp| index_from_ijk(i, jp, jp)).map(index_to_bit).sum()
                 );
             }
 
             if j == N - 1 - k {
                 masks.push(
-                    (0..N).filter(|&jp| jp != j).map(|jp| index_from_ijk(i, jp, N - 1 - jp)).map(|b| index_to_bit(b)).sum()
+                    (0..N).filter(|&jp| jp != j).map(|jp| index_from_ijk(i, jp, N - 1 - jp)).map(index_to_bit).sum()
                 );
             }
 
             // i - j - k
             if i == j && i == k {
                 masks.push(
-                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, ip, ip)).map(|b| index_to_bit(b)).sum()
+                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, ip, ip)).map(index_to_bit).sum()
                 );
             }
 
             if i == N - 1 - j && i == k {
                 masks.push(
-                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, N - 1 - ip, ip)).map(|b| index_to_bit(b)).sum()
+                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, N - 1 - ip, ip)).map(index_to_bit).sum()
                 );
             }
 
             if i == j && i == N - 1 - k {
                 masks.push(
-                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, ip, N - 1 - ip)).map(|b| index_to_bit(b)).sum()
+                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, ip, N - 1 - ip)).map(index_to_bit).sum()
                 );
             }
 
             if i == N - 1 - j && i == N - 1 - k {
                 masks.push(
-                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, N - 1 - ip, N - 1 - ip)).map(|b| index_to_bit(b)).sum()
+                    (0..N).filter(|&ip| ip != i).map(|ip| index_from_ijk(ip, N - 1 - ip, N - 1 - ip)).map(index_to_bit).sum()
                 );
             }
 
@@ -113,8 +113,67 @@ lazy_static! {
         }
         vec
     };
+
+    /**
+     * 全76本のライン
+     */
+    static ref LINE_TABLE: Vec<u64> = {
+        let mut lines = CHECK_MASK_TABLE
+            .iter()
+            .enumerate()
+            .flat_map(|(index, masks)| masks.iter().map(move |mask| mask | index_to_bit(index)))
+            .collect::<Vec<_>>();
+        lines.sort_unstable();
+        lines.dedup();
+        lines
+    };
+
+    /**
+     * 盤面の対称変換。重力があるので、kを保ったまま(i, j)を正方形の二面体群で写す。
+     */
+    static ref SYMMETRY_TABLE: Vec<[usize; N * N * N]> = {
+        (0..N_SYMMETRY)
+            .map(|sym| {
+                let mut table = [0; N * N * N];
+                for (index, to) in table.iter_mut().enumerate() {
+                    let (i, j, k) = index_to_ijk(index);
+                    let (i, j) = if sym & 4 != 0 { (j, i) } else { (i, j) };
+                    let i = if sym & 1 != 0 { N - 1 - i } else { i };
+                    let j = if sym & 2 != 0 { N - 1 - j } else { j };
+                    *to = index_from_ijk(i, j, k);
+                }
+                table
+            })
+            .collect()
+    };
+
+    /**
+     * 一段分(N * N = 16ビット)の対称変換を、下位と上位の1バイトずつ引く表。
+     * 変換はkを保つので、どの段にも同じ表が使える。
+     */
+    static ref LAYER_SYMMETRY_TABLE: Vec<[[u16; 256]; 2]> = {
+        SYMMETRY_TABLE
+            .iter()
+            .map(|table| {
+                let mut layer = [[0u16; 256]; 2];
+                for (half, bytes) in layer.iter_mut().enumerate() {
+                    for (byte, to) in bytes.iter_mut().enumerate() {
+                        for bit in (0..8).filter(|bit| byte & (1 << bit) != 0) {
+                            *to |= 1 << table[half * 8 + bit];
+                        }
+                    }
+                }
+                layer
+            })
+            .collect()
+    };
 }
 
+/**
+ * number of board symmetries
+ */
+pub const N_SYMMETRY: usize = 8;
+
 const BLACK_INDEX: usize = 0;
 const WHITE_INDEX: usize = 1;
 
@@ -125,26 +184,27 @@ fn player_index(player: Player) -> usize {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BitBoard {
     pub(crate) boards: [u64; 2],
     pub(crate) next_player: Player,
 }
 
-impl Into<ArrayBoard> for &BitBoard {
-    fn into(self) -> ArrayBoard {
+impl From<&BitBoard> for ArrayBoard {
+    fn from(board: &BitBoard) -> Self {
         let mut result = ArrayBoard::new();
-        result.next_player = self.next_player;
+        result.next_player = board.next_player;
         for i in 0..N {
             for j in 0..N {
                 for k in 0..N {
                     let index = index_from_ijk(i, j, k);
-                    result.board[i * N + j][k] = if self.boards[player_index(Player::Black)]
+                    result.board[i * N + j][k] = if board.boards[player_index(Player::Black)]
                         & index_to_bit(index)
                         != 0
                     {
                         board::Piece::Black
-                    } else if self.boards[player_index(Player::White)] & index_to_bit(index) != 0 {
+                    } else if board.boards[player_index(Player::White)] & index_to_bit(index) != 0 {
                         board::Piece::White
                     } else {
                         board::Piece::Empty
@@ -156,6 +216,36 @@ impl Into<ArrayBoard> for &BitBoard {
     }
 }
 
+impl From<&ArrayBoard> for BitBoard {
+    fn from(board: &ArrayBoard) -> Self {
+        let mut result = BitBoard::new();
+        result.next_player = board.next_player;
+        for i in 0..N {
+            for j in 0..N {
+                for k in 0..N {
+                    let index = index_from_ijk(i, j, k);
+                    match board.board[i * N + j][k] {
+                        board::Piece::Black => {
+                            result.boards[player_index(Player::Black)] |= index_to_bit(index)
+                        }
+                        board::Piece::White => {
+                            result.boards[player_index(Player::White)] |= index_to_bit(index)
+                        }
+                        board::Piece::Empty => (),
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+impl Default for BitBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BitBoard {
     pub fn new() -> BitBoard {
         BitBoard {
@@ -164,25 +254,105 @@ impl BitBoard {
         }
     }
 
-    pub fn show(&self) {
-        for i in 0..N {
-            for k in 0..N {
-                for j in 0..N {
-                    let index = index_from_ijk(i, j, k);
+    pub fn next_player(&self) -> Player {
+        self.next_player
+    }
 
-                    if self.boards[player_index(Player::Black)] & index_to_bit(index) != 0 {
-                        print!("b");
-                    } else if self.boards[player_index(Player::White)] & index_to_bit(index) != 0 {
-                        print!("w");
-                    } else {
-                        print!("e");
-                    }
-                }
-                print!("\t")
+    /**
+     * 位置文字列。各列の石を下から b/w で並べ、列を'/'で区切る。
+     */
+    pub fn position_string(&self) -> String {
+        (0..N * N)
+            .map(|index_2d| {
+                (0..N)
+                    .map(|k| index_to_bit(index_from_index_2d(index_2d, k)))
+                    .map_while(|bit| {
+                        if self.boards[player_index(Player::Black)] & bit != 0 {
+                            Some('b')
+                        } else if self.boards[player_index(Player::White)] & bit != 0 {
+                            Some('w')
+                        } else {
+                            None
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    pub fn from_position_string(position: &str) -> Option<Self> {
+        let columns = position.trim().split('/').collect::<Vec<_>>();
+        if columns.len() != N * N {
+            return None;
+        }
+        let mut boards = [0u64, 0u64];
+        for (index_2d, column) in columns.iter().enumerate() {
+            if column.len() > N {
+                return None;
+            }
+            for (k, c) in column.chars().enumerate() {
+                let player = match c {
+                    'b' => Player::Black,
+                    'w' => Player::White,
+                    _ => return None,
+                };
+                boards[player_index(player)] |= index_to_bit(index_from_index_2d(index_2d, k));
             }
-            print!("\n");
         }
-        print!("\n");
+        let n_black = boards[player_index(Player::Black)].count_ones();
+        let n_white = boards[player_index(Player::White)].count_ones();
+        let next_player = if n_black == n_white {
+            Player::Black
+        } else if n_black == n_white + 1 {
+            Player::White
+        } else {
+            return None;
+        };
+        Some(BitBoard {
+            boards,
+            next_player,
+        })
+    }
+
+    pub fn piece(&self, index_2d: usize, k: usize) -> board::Piece {
+        let bit = index_to_bit(index_from_index_2d(index_2d, k));
+        if self.boards[player_index(Player::Black)] & bit != 0 {
+            board::Piece::Black
+        } else if self.boards[player_index(Player::White)] & bit != 0 {
+            board::Piece::White
+        } else {
+            board::Piece::Empty
+        }
+    }
+
+    /**
+     * number of stones in the column
+     */
+    pub fn height(&self, index_2d: usize) -> usize {
+        let board: u64 = self.boards.iter().sum();
+        (0..N)
+            .take_while(|&k| board & index_to_bit(index_from_index_2d(index_2d, k)) != 0)
+            .count()
+    }
+
+    /**
+     * playerが置けばラインが完成する空きマス(index_2d, k)。まだ置けない高さのものも含む。
+     */
+    pub fn threats(&self, player: Player) -> Vec<(usize, usize)> {
+        let mine = self.boards[player_index(player)];
+        let empty = !self.boards.iter().sum::<u64>();
+        let mut threats = LINE_TABLE
+            .iter()
+            .filter(|&&line| (line & !mine).count_ones() == 1 && line & !mine & empty != 0)
+            .map(|&line| {
+                let (i, j, k) = index_to_ijk((line & !mine).trailing_zeros() as usize);
+                (i * N + j, k)
+            })
+            .collect::<Vec<_>>();
+        threats.sort_unstable();
+        threats.dedup();
+        threats
     }
 
     pub fn find_index(&self, index_2d: usize) -> Option<usize> {
@@ -239,12 +409,12 @@ impl BitBoard {
                         // 2石が揃っている。3石目。
                         let last_one = ((!my_piece) & mask) & ((!opp_piece) & mask);
                         // 下の段が埋まっているか。
-                        if boards & (last_one >> N * N) != 0 {
+                        if boards & (last_one >> (N * N)) != 0 {
                             // ただのリーチ
                             1
                         } else {
                             // トラップ
-                            if last_one & 0xFFFF << 2 * N * N != 0 {
+                            if last_one & 0xFFFF << (2 * N * N) != 0 {
                                 // 三段目トラップ
                                 8
                             } else {
@@ -275,10 +445,84 @@ impl BitBoard {
         Some((self.put_without_check(index), self.count_policy(index)))
     }
 
+    /**
+     * putと同じだが、置けない理由を返す
+     */
+    pub fn try_play(&self, index_2d: usize) -> Result<Self, MoveError> {
+        if index_2d >= N * N {
+            return Err(MoveError::OutOfRange(index_2d));
+        }
+        if self.is_won() || self.is_full() {
+            return Err(MoveError::GameOver);
+        }
+        self.put(index_2d).ok_or(MoveError::ColumnFull(index_2d))
+    }
+
     pub fn is_full(&self) -> bool {
         (!self.boards.iter().sum::<u64>()) == 0u64
     }
 
+    /**
+     * return true if the player has completed a line
+     */
+    pub fn has_line(&self, player: Player) -> bool {
+        let board = self.boards[player_index(player)];
+        LINE_TABLE.iter().any(|&line| line & !board == 0)
+    }
+
+    /**
+     * return true if the player who has just moved completed a line
+     */
+    pub fn is_won(&self) -> bool {
+        self.has_line(self.next_player.next_player())
+    }
+
+    /**
+     * 直前に打ったプレイヤーが完成させたラインのマス(index_2d, k)
+     */
+    pub fn winning_line(&self) -> Option<Vec<(usize, usize)>> {
+        let board = self.boards[player_index(self.next_player.next_player())];
+        let line = LINE_TABLE.iter().find(|&&line| line & !board == 0)?;
+        Some(
+            (0..N * N * N)
+                .filter(|&index| line & index_to_bit(index) != 0)
+                .map(|index| {
+                    let (i, j, k) = index_to_ijk(index);
+                    (i * N + j, k)
+                })
+                .collect(),
+        )
+    }
+
+    /**
+     * 対称変換を施した盤面を返す。symは0..N_SYMMETRY
+     */
+    pub fn symmetric(&self, sym: usize) -> Self {
+        let table = &LAYER_SYMMETRY_TABLE[sym];
+        let mut result = BitBoard {
+            boards: [0u64, 0u64],
+            next_player: self.next_player,
+        };
+        for p in 0..2 {
+            for k in 0..N {
+                let layer = (self.boards[p] >> (k * N * N)) as usize;
+                let to = table[0][layer & 0xff] | table[1][(layer >> 8) & 0xff];
+                result.boards[p] |= (to as u64) << (k * N * N);
+            }
+        }
+        result
+    }
+
+    /**
+     * 対称な盤面のうち代表となるもの
+     */
+    pub fn canonical(&self) -> Self {
+        (0..N_SYMMETRY)
+            .map(|sym| self.symmetric(sym))
+            .min_by_key(|board| board.boards)
+            .unwrap()
+    }
+
     fn win_index_2d_player(&self, player: Player) -> Option<usize> {
         for i in 0..N {
             for j in 0..N {
@@ -317,6 +561,103 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_position_string() {
+        let board = BitBoard::new();
+        assert_eq!(board.position_string(), "///////////////");
+
+        let board = board.put(5).unwrap().put(5).unwrap().put(15).unwrap();
+        let position = board.position_string();
+        assert_eq!(position, "/////bw//////////b");
+        let parsed = BitBoard::from_position_string(&position).unwrap();
+        assert_eq!(parsed, board);
+        assert_eq!(parsed.next_player, Player::White);
+
+        assert!(BitBoard::from_position_string("bbbbw///////////////").is_none());
+        assert!(BitBoard::from_position_string("bb///////////////").is_none());
+        assert!(BitBoard::from_position_string("w///////////////").is_none());
+    }
+
+    #[test]
+    fn test_try_play() {
+        let mut board = BitBoard::new();
+        assert_eq!(board.try_play(N * N), Err(MoveError::OutOfRange(N * N)));
+        for _ in 0..N {
+            board = board.try_play(0).unwrap();
+        }
+        assert_eq!(board.try_play(0), Err(MoveError::ColumnFull(0)));
+        assert_eq!(
+            ArrayBoard::from(&board).try_play(0).err(),
+            Some(MoveError::ColumnFull(0))
+        );
+
+        for index in [1, 5, 2, 6, 3] {
+            board = board.try_play(index).unwrap();
+        }
+        assert!(board.is_won());
+        assert_eq!(board.try_play(7), Err(MoveError::GameOver));
+        assert_eq!(MoveError::ColumnFull(6).to_string(), "column c2 is full");
+        assert_eq!(
+            MoveError::OutOfRange(16).to_string(),
+            "there is no column 16 (a1..d4)"
+        );
+    }
+
+    #[test]
+    fn test_symmetric() {
+        for _ in 0..1000 {
+            let board = BitBoard {
+                boards: [rand::random(), rand::random()],
+                next_player: Player::White,
+            };
+            for (sym, table) in SYMMETRY_TABLE.iter().enumerate() {
+                let mut expected = [0u64; 2];
+                for (index, &to) in table.iter().enumerate() {
+                    for (p, bits) in expected.iter_mut().enumerate() {
+                        if board.boards[p] & index_to_bit(index) != 0 {
+                            *bits |= index_to_bit(to);
+                        }
+                    }
+                }
+                assert_eq!(board.symmetric(sym).boards, expected);
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut board = BitBoard::new();
+        while !board.is_won() && !board.is_full() {
+            let json = serde_json::to_string(&board).unwrap();
+            let restored: BitBoard = serde_json::from_str(&json).unwrap();
+            assert_eq!(restored, board);
+
+            let array = ArrayBoard::from(&board);
+            let json = serde_json::to_string(&array).unwrap();
+            let restored_array: ArrayBoard = serde_json::from_str(&json).unwrap();
+            assert_eq!(restored_array.win_index(), array.win_index());
+
+            for index in 0..N * N {
+                assert_eq!(restored.put(index), board.put(index));
+                assert_eq!(
+                    restored_array.put(index).map(|b| BitBoard::from(&b)),
+                    array.put(index).map(|b| BitBoard::from(&b))
+                );
+            }
+            assert_eq!(restored.win_index(), board.win_index());
+            assert_eq!(restored.check_index(), board.check_index());
+
+            let index = loop {
+                let index = rand::random::<usize>() % (N * N);
+                if board.height(index) < N {
+                    break index;
+                }
+            };
+            board = board.put(index).unwrap();
+        }
+    }
+
     #[test]
     fn test_ab() {
         for _ in 0..10000 {
@@ -332,6 +673,10 @@ mod tests {
                 let index = (rand::random::<usize>()) % (N * N);
                 let bt = bitboard.put(index);
                 let at = arrayboard.put(index);
+                assert_eq!(
+                    arrayboard.try_play(index).err(),
+                    bitboard.try_play(index).err()
+                );
 
                 assert_eq!(at.is_none(), bt.is_none());
 
@@ -344,6 +689,14 @@ mod tests {
 
                 assert_eq!(arrayboard.check_index(), bitboard.check_index());
                 assert_eq!(arrayboard.win_index(), bitboard.win_index());
+                assert_eq!(arrayboard.is_won(), bitboard.is_won());
+                assert_eq!(
+                    bitboard.win_index().is_some(),
+                    bitboard
+                        .threats(bitboard.next_player)
+                        .iter()
+                        .any(|&(index_2d, k)| bitboard.height(index_2d) == k)
+                );
 
                 if arrayboard.win_index().is_some() {
                     break;
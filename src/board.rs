@@ -1,4 +1,6 @@
-use crate::N;
+use std::{error::Error, fmt};
+
+use crate::{format_column, N};
 
 /**
  * State for board piece
@@ -6,6 +8,7 @@ use crate::N;
  */
 #[repr(u8)]
 #[derive(Default, Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Piece {
     #[default]
     Empty,
@@ -16,15 +19,16 @@ pub enum Piece {
 /**
  * Player enum
  */
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Player {
     Black,
     White,
 }
 
-impl Into<Piece> for Player {
-    fn into(self) -> Piece {
-        match self {
+impl From<Player> for Piece {
+    fn from(player: Player) -> Self {
+        match player {
             Player::Black => Piece::Black,
             Player::White => Piece::White,
         }
@@ -40,12 +44,50 @@ impl Player {
     }
 }
 
+/**
+ * 石を置けなかった理由
+ */
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MoveError {
+    /// index_2dがN*N以上
+    OutOfRange(usize),
+    /// 列が一番上まで埋まっている
+    ColumnFull(usize),
+    /// どちらかがラインを完成させたか、盤が埋まっている
+    GameOver,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::OutOfRange(index) => write!(
+                f,
+                "there is no column {} ({}..{})",
+                index,
+                format_column(0),
+                format_column(N * N - 1)
+            ),
+            MoveError::ColumnFull(index) => write!(f, "column {} is full", format_column(*index)),
+            MoveError::GameOver => write!(f, "the game is already over"),
+        }
+    }
+}
+
+impl Error for MoveError {}
+
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ArrayBoard {
     pub(crate) board: [[Piece; N]; N * N],
     pub(crate) next_player: Player,
 }
 
+impl Default for ArrayBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ArrayBoard {
     pub fn new() -> ArrayBoard {
         ArrayBoard {
@@ -54,24 +96,6 @@ impl ArrayBoard {
         }
     }
 
-    pub fn show(&self) {
-        for i in 0..N {
-            for z in 0..N {
-                for j in 0..N {
-                    let index = i * N + j;
-                    match self.board[index][z] {
-                        Piece::Empty => print!("e"),
-                        Piece::Black => print!("b"),
-                        Piece::White => print!("w"),
-                    }
-                }
-                print!("\t")
-            }
-            print!("\n");
-        }
-        print!("\n");
-    }
-
     pub fn find_index(&self, index: usize) -> Option<usize> {
         self.board[index]
             .iter()
@@ -91,10 +115,62 @@ impl ArrayBoard {
         Some(new_board)
     }
 
+    /**
+     * putと同じだが、置けない理由を返す
+     */
+    pub fn try_play(&self, index: usize) -> Result<Self, MoveError> {
+        if index >= N * N {
+            return Err(MoveError::OutOfRange(index));
+        }
+        if self.is_won() || self.is_full() {
+            return Err(MoveError::GameOver);
+        }
+        self.put(index).ok_or(MoveError::ColumnFull(index))
+    }
+
     pub fn is_full(&self) -> bool {
         self.board
             .iter()
-            .all(|ps| ps.into_iter().all(|&p| p != Piece::Empty))
+            .all(|ps| ps.iter().all(|&p| p != Piece::Empty))
+    }
+
+    /**
+     * return true if the player has completed a line
+     */
+    pub fn has_line(&self, player: Player) -> bool {
+        let piece: Piece = player.into();
+        let n = N as isize;
+        let at = |i: isize, j: isize, k: isize| {
+            (0..n).contains(&i)
+                && (0..n).contains(&j)
+                && (0..n).contains(&k)
+                && self.board[(i * n + j) as usize][k as usize] == piece
+        };
+        // 13方向
+        let directions = (-1..=1)
+            .flat_map(|di| (-1..=1).flat_map(move |dj| (-1..=1).map(move |dk| (di, dj, dk))))
+            .filter(|&d| d > (0, 0, 0))
+            .collect::<Vec<_>>();
+        for i in 0..n {
+            for j in 0..n {
+                for k in 0..n {
+                    if directions
+                        .iter()
+                        .any(|&(di, dj, dk)| (0..n).all(|t| at(i + di * t, j + dj * t, k + dk * t)))
+                    {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /**
+     * return true if the player who has just moved completed a line
+     */
+    pub fn is_won(&self) -> bool {
+        self.has_line(self.next_player.next_player())
     }
 
     fn win_index_player(&self, player: Player) -> Option<usize> {
@@ -207,7 +283,7 @@ mod tests {
     #[test]
     fn test_win() {
         let mut board = ArrayBoard::new();
-        assert_eq!(board.win_index().is_some(), false);
+        assert!(board.win_index().is_none());
         for k in 0..N * N {
             board = ArrayBoard::new();
             for i in 0..N - 1 {
@@ -0,0 +1,558 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+};
+
+use crate::{
+    bitboard::BitBoard,
+    board::Player,
+    proof::{ProofNode, ProofTree},
+    search::Proof,
+    N,
+};
+
+/**
+ * 証明数と反証数の無限大
+ */
+const INF: u32 = u32::MAX;
+
+/**
+ * 表の一項目が使うおおよそのバイト数。HashMapの空きも含める。
+ */
+pub const ENTRY_BYTES: usize = 64;
+
+/**
+ * 表の一項目。手番側から見た数で、phiが0なら手番側が目的を果たせる。
+ * 手番側が攻め方ならphiは証明数、deltaは反証数。受け方なら逆になる。
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Entry {
+    phi: u32,
+    delta: u32,
+}
+
+impl Entry {
+    const UNKNOWN: Entry = Entry { phi: 1, delta: 1 };
+    /// 手番側が目的を果たした
+    const WIN: Entry = Entry { phi: 0, delta: INF };
+    /// 手番側が目的を果たせない
+    const LOSE: Entry = Entry { phi: INF, delta: 0 };
+}
+
+/**
+ * 調べる子。keyとterminalは一度だけ求める。
+ */
+struct Child {
+    hand: usize,
+    board: BitBoard,
+    key: BitBoard,
+    terminal: Option<Entry>,
+}
+
+/**
+ * df-pnで、attackerが勝てるかどうかを調べる。
+ * 局面は表に残るので、同じDfpnで続けて呼べば前の探索の続きになる。
+ */
+#[derive(Clone, Debug)]
+pub struct Dfpn {
+    attacker: Player,
+    /// 対称な局面を同じものとして扱う
+    symmetry: bool,
+    table: HashMap<BitBoard, Entry>,
+    nodes: u64,
+    /// nodesがこれに達したら打ち切る
+    limit: u64,
+}
+
+impl Dfpn {
+    pub fn new(attacker: Player) -> Self {
+        Dfpn {
+            attacker,
+            symmetry: true,
+            table: HashMap::new(),
+            nodes: 0,
+            limit: u64::MAX,
+        }
+    }
+
+    pub fn with_symmetry(mut self, symmetry: bool) -> Self {
+        self.symmetry = symmetry;
+        self
+    }
+
+    pub fn attacker(&self) -> Player {
+        self.attacker
+    }
+
+    /**
+     * これまでに調べたノードの数
+     */
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /**
+     * 表に入っている局面の数
+     */
+    pub fn table_len(&self) -> usize {
+        self.table.len()
+    }
+
+    fn key(&self, board: &BitBoard) -> BitBoard {
+        if self.symmetry {
+            board.canonical()
+        } else {
+            board.clone()
+        }
+    }
+
+    /**
+     * 子を作らずにわかる結果
+     */
+    fn terminal(&self, board: &BitBoard) -> Option<Entry> {
+        if board.win_index().is_some() {
+            Some(Entry::WIN)
+        } else if board.is_full() {
+            // 引き分けは攻め方の失敗
+            if board.next_player() == self.attacker {
+                Some(Entry::LOSE)
+            } else {
+                Some(Entry::WIN)
+            }
+        } else {
+            None
+        }
+    }
+
+    fn entry(&self, board: &BitBoard) -> Entry {
+        self.terminal(board)
+            .unwrap_or_else(|| self.lookup(&self.key(board)))
+    }
+
+    fn lookup(&self, key: &BitBoard) -> Entry {
+        self.table.get(key).copied().unwrap_or(Entry::UNKNOWN)
+    }
+
+    fn child_entry(&self, child: &Child) -> Entry {
+        child.terminal.unwrap_or_else(|| self.lookup(&child.key))
+    }
+
+    /**
+     * 調べる子。王手がかかっていれば塞ぐ手だけで、他の手はすぐ負ける。
+     * 対称な子は一つにまとめる。
+     */
+    fn children(&self, board: &BitBoard) -> Vec<Child> {
+        let hands = match board.check_index() {
+            Some(index) => vec![index],
+            None => (0..N * N).collect(),
+        };
+        let mut children: Vec<Child> = vec![];
+        for hand in hands {
+            if let Some(child) = board.put(hand) {
+                let key = self.key(&child);
+                if children.iter().all(|c| c.key != key) {
+                    children.push(Child {
+                        hand,
+                        terminal: self.terminal(&child),
+                        board: child,
+                        key,
+                    });
+                }
+            }
+        }
+        children
+    }
+
+    /**
+     * phiかdeltaがしきい値に届くまで、最も有望な子を掘り下げる
+     */
+    fn mid(&mut self, board: &BitBoard, phi_threshold: u32, delta_threshold: u32) {
+        self.nodes += 1;
+        let children = self.children(board);
+        loop {
+            // phiは子のdeltaの最小、deltaは子のphiの和
+            let mut phi = INF;
+            let mut delta: u32 = 0;
+            let mut best = None;
+            let mut second = INF;
+            for (i, child) in children.iter().enumerate() {
+                let entry = self.child_entry(child);
+                if entry.delta < phi {
+                    second = phi;
+                    phi = entry.delta;
+                    best = Some((i, entry));
+                } else if entry.delta < second {
+                    second = entry.delta;
+                }
+                delta = if entry.phi == INF || delta == INF {
+                    INF
+                } else {
+                    delta.saturating_add(entry.phi).min(INF - 1)
+                };
+            }
+            let Some((i, best)) = best.filter(|_| {
+                phi < phi_threshold && delta < delta_threshold && self.nodes < self.limit
+            }) else {
+                let key = self.key(board);
+                self.table.insert(key, Entry { phi, delta });
+                return;
+            };
+            let child = children[i].board.clone();
+            let child_phi = delta_threshold - (delta - best.phi);
+            let child_delta = phi_threshold.min(second.saturating_add(1));
+            self.mid(&child, child_phi, child_delta);
+        }
+    }
+
+    /**
+     * 最大max_nodesノードまで調べる。
+     * return attackerが勝てるならSome(true)、勝てないならSome(false)、わからなければNone
+     */
+    pub fn search(&mut self, board: &BitBoard, max_nodes: u64) -> Option<bool> {
+        self.limit = self.nodes.saturating_add(max_nodes);
+        loop {
+            if let Some(proven) = self.proven(board) {
+                return Some(proven);
+            }
+            if self.nodes >= self.limit {
+                return None;
+            }
+            self.mid(board, INF - 1, INF - 1);
+        }
+    }
+
+    /**
+     * 表からわかる結果。attackerが勝てるならSome(true)、勝てないならSome(false)
+     */
+    pub fn proven(&self, board: &BitBoard) -> Option<bool> {
+        let entry = self.entry(board);
+        // phiが0なら手番側が目的を果たす
+        (entry.phi == 0 || entry.delta == 0)
+            .then(|| (entry.phi == 0) == (board.next_player() == self.attacker))
+    }
+
+    /**
+     * attackerが勝てる局面で、証明木を作る。表にない局面は読み直す。
+     * 合流や対称な局面もまとめずに展開するので、空きの少ない局面向け。
+     */
+    pub fn proof_tree(&mut self, board: &BitBoard) -> Option<ProofTree> {
+        if board.is_won() || !self.search(board, u64::MAX)? {
+            return None;
+        }
+        Some(ProofTree {
+            root: board.clone(),
+            winner: self.attacker,
+            moves: self.proof_moves(board)?,
+        })
+    }
+
+    fn proof_moves(&mut self, board: &BitBoard) -> Option<Vec<ProofNode>> {
+        if board.next_player() == self.attacker {
+            let hand = match self.proven_move(board) {
+                Some(hand) => hand,
+                None => {
+                    self.search(board, u64::MAX).filter(|&win| win)?;
+                    self.proven_move(board)?
+                }
+            };
+            let next = board.put(hand)?;
+            let children = if next.is_won() {
+                vec![]
+            } else {
+                self.proof_moves(&next)?
+            };
+            Some(vec![ProofNode { hand, children }])
+        } else {
+            (0..N * N)
+                .filter_map(|hand| Some((hand, board.put(hand)?)))
+                .map(|(hand, next)| {
+                    let children = self.proof_moves(&next)?;
+                    Some(ProofNode { hand, children })
+                })
+                .collect()
+        }
+    }
+
+    /**
+     * 表がmax_entriesを超えていたら、半分になるまで読み切れていない局面を捨てる。
+     * phiとdeltaの小さい、調べ直すのが安い局面から捨てる。読み切った局面は残す。
+     */
+    pub fn shrink(&mut self, max_entries: usize) {
+        if self.table.len() <= max_entries {
+            return;
+        }
+        let mut threshold = 1;
+        loop {
+            self.table.retain(|_, entry| {
+                entry.phi == 0 || entry.delta == 0 || entry.phi.min(entry.delta) > threshold
+            });
+            if self.table.len() <= max_entries / 2 || threshold == INF {
+                return;
+            }
+            threshold = threshold.saturating_mul(2);
+        }
+    }
+
+    /**
+     * 表と調べたノード数を書き出す
+     */
+    pub fn save(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(&[self.attacker as u8, self.symmetry as u8])?;
+        w.write_all(&self.nodes.to_le_bytes())?;
+        w.write_all(&(self.table.len() as u64).to_le_bytes())?;
+        for (key, entry) in &self.table {
+            write_board(w, key)?;
+            w.write_all(&entry.phi.to_le_bytes())?;
+            w.write_all(&entry.delta.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /**
+     * saveで書き出したものを読み込む
+     */
+    pub fn load(r: &mut impl Read) -> io::Result<Self> {
+        let mut flags = [0u8; 2];
+        r.read_exact(&mut flags)?;
+        let mut dfpn = Dfpn::new(read_player(flags[0])?).with_symmetry(flags[1] != 0);
+        dfpn.nodes = read_u64(r)?;
+        let len = read_u64(r)?;
+        for _ in 0..len {
+            let key = read_board(r)?;
+            let phi = read_u32(r)?;
+            let delta = read_u32(r)?;
+            dfpn.table.insert(key, Entry { phi, delta });
+        }
+        Ok(dfpn)
+    }
+
+    /**
+     * 手番側が目的を果たせるとわかっている局面で、そのための手
+     */
+    pub fn proven_move(&self, board: &BitBoard) -> Option<usize> {
+        if let Some(index) = board.win_index() {
+            return Some(index);
+        }
+        self.children(board)
+            .into_iter()
+            .find(|child| self.child_entry(child).delta == 0)
+            .map(|child| child.hand)
+    }
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_player(byte: u8) -> io::Result<Player> {
+    match byte {
+        b if b == Player::Black as u8 => Ok(Player::Black),
+        b if b == Player::White as u8 => Ok(Player::White),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid player")),
+    }
+}
+
+pub(crate) fn write_board(w: &mut impl Write, board: &BitBoard) -> io::Result<()> {
+    w.write_all(&board.boards[0].to_le_bytes())?;
+    w.write_all(&board.boards[1].to_le_bytes())?;
+    w.write_all(&[board.next_player as u8])
+}
+
+pub(crate) fn read_board(r: &mut impl Read) -> io::Result<BitBoard> {
+    let boards = [read_u64(r)?, read_u64(r)?];
+    let mut next_player = [0u8];
+    r.read_exact(&mut next_player)?;
+    Ok(BitBoard {
+        boards,
+        next_player: read_player(next_player[0])?,
+    })
+}
+
+/**
+ * proveの結果
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ProofResult {
+    /// 手番側から見た結果。読み切れなければNone
+    pub proof: Option<Proof>,
+    /// 勝ちなら勝つ手、引き分けなら引き分けにできる手
+    pub best: Option<usize>,
+    pub nodes: u64,
+}
+
+/**
+ * 手番側が勝てるか、負けるか、引き分けかをdf-pnで読み切る。
+ * まず手番側の勝ちを調べ、勝てなければ相手の勝ちを調べる。合わせてmax_nodesノードまで。
+ */
+pub fn prove(board: &BitBoard, max_nodes: u64) -> ProofResult {
+    prove_with_table(board, max_nodes).0
+}
+
+/**
+ * proveと同じ。どちらかの勝ちとわかれば、それを示した表も返す。証明木はその表から作れる。
+ */
+pub fn prove_with_table(board: &BitBoard, max_nodes: u64) -> (ProofResult, Option<Dfpn>) {
+    let result = |proof, best, nodes| ProofResult { proof, best, nodes };
+    if board.is_won() {
+        return (result(Some(Proof::Lose), None, 0), None);
+    }
+    if board.is_full() {
+        return (result(Some(Proof::Draw), None, 0), None);
+    }
+
+    let player = board.next_player();
+    let mut dfpn = Dfpn::new(player);
+    match dfpn.search(board, max_nodes) {
+        Some(true) => {
+            let best = dfpn.proven_move(board);
+            let nodes = dfpn.nodes();
+            return (result(Some(Proof::Win), best, nodes), Some(dfpn));
+        }
+        Some(false) => (),
+        None => return (result(None, None, dfpn.nodes()), None),
+    }
+
+    let mut opponent = Dfpn::new(player.next_player());
+    let nodes = dfpn.nodes();
+    match opponent.search(board, max_nodes.saturating_sub(nodes)) {
+        Some(true) => {
+            let nodes = nodes + opponent.nodes();
+            (result(Some(Proof::Lose), None, nodes), Some(opponent))
+        }
+        Some(false) => (
+            result(
+                Some(Proof::Draw),
+                opponent.proven_move(board),
+                nodes + opponent.nodes(),
+            ),
+            None,
+        ),
+        None => (result(None, None, nodes + opponent.nodes()), None),
+    }
+}
+
+/**
+ * テスト用。勝負のつかないまま、空きがemptyになるまで打った局面
+ */
+#[cfg(test)]
+pub(crate) fn endgame(seed: u64, empty: usize) -> BitBoard {
+    use rand::Rng;
+    let mut rng = crate::search::rng_stream(seed, 0);
+    'retry: loop {
+        let mut board = BitBoard::new();
+        while (0..N * N).map(|i| N - board.height(i)).sum::<usize>() > empty {
+            let Some(next) = board.put(rng.gen_range(0..N * N)) else {
+                continue;
+            };
+            if next.is_won() {
+                continue 'retry;
+            }
+            board = next;
+        }
+        return board;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minimax(board: &BitBoard) -> Proof {
+        if board.is_won() {
+            return Proof::Lose;
+        }
+        if board.win_index().is_some() {
+            return Proof::Win;
+        }
+        if board.is_full() {
+            return Proof::Draw;
+        }
+        Proof::from_children((0..N * N).filter_map(|i| Some(Some(minimax(&board.put(i)?)))))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_prove() {
+        for seed in 0..20 {
+            let board = endgame(seed, 8);
+            let expected = minimax(&board);
+            let result = prove(&board, u64::MAX);
+            assert_eq!(result.proof, Some(expected), "{}", board.position_string());
+            if expected != Proof::Lose {
+                let best = board.put(result.best.unwrap()).unwrap();
+                assert_eq!(minimax(&best).flip(), expected);
+            }
+            let (_, table) = prove_with_table(&board, u64::MAX);
+            assert_eq!(table.is_some(), expected != Proof::Draw);
+            if let Some(mut table) = table {
+                let nodes = table.nodes();
+                let tree = table.proof_tree(&board).unwrap();
+                assert!(crate::proof::verify(&tree).is_ok());
+                assert_eq!(table.nodes(), nodes);
+            }
+            let result = Dfpn::new(board.next_player())
+                .with_symmetry(false)
+                .search(&board, u64::MAX);
+            assert_eq!(result, Some(expected == Proof::Win));
+        }
+    }
+
+    #[test]
+    fn test_budget() {
+        let board = BitBoard::new();
+        let result = prove(&board, 100);
+        assert_eq!(result.proof, None);
+        assert!(result.nodes <= 101);
+
+        // 続けて呼べば前の続きから調べる
+        let board = endgame(3, 9);
+        let mut dfpn = Dfpn::new(board.next_player());
+        let mut answer = None;
+        while answer.is_none() {
+            answer = dfpn.search(&board, 10);
+        }
+        assert_eq!(answer, Some(minimax(&board) == Proof::Win));
+    }
+
+    #[test]
+    fn test_save_and_shrink() {
+        let (board, dfpn) = (0..)
+            .map(|seed| {
+                let board = endgame(seed, 10);
+                let mut dfpn = Dfpn::new(board.next_player());
+                let answer = dfpn.search(&board, 10);
+                (board, dfpn, answer)
+            })
+            .find_map(|(board, dfpn, answer)| answer.is_none().then_some((board, dfpn)))
+            .unwrap();
+
+        // 書き出して読み込んでも続きから調べられる
+        let mut bytes = vec![];
+        dfpn.save(&mut bytes).unwrap();
+        let mut loaded = Dfpn::load(&mut bytes.as_slice()).unwrap();
+        assert_eq!(loaded.nodes(), dfpn.nodes());
+        assert_eq!(loaded.table_len(), dfpn.table_len());
+        assert!(Dfpn::load(&mut &bytes[..bytes.len() - 1]).is_err());
+
+        let expected = minimax(&board) == Proof::Win;
+        loop {
+            loaded.shrink(20);
+            assert!(loaded.table_len() <= 20 || loaded.proven(&board).is_some());
+            if let Some(answer) = loaded.search(&board, 10) {
+                assert_eq!(answer, expected);
+                break;
+            }
+        }
+        // 読み切った局面は捨てない
+        loaded.shrink(0);
+        assert_eq!(loaded.proven(&board), Some(expected));
+    }
+}
@@ -0,0 +1,268 @@
+use std::fmt;
+
+use crate::{bitboard::BitBoard, board::Player, format_column, N};
+
+/**
+ * その手を勧める理由。手番側から見る。
+ */
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Reason {
+    /// ラインが完成する
+    Win,
+    /// 相手が次に完成させるマスを塞ぐ
+    Block,
+    /// 次に勝てるマスが二つでき、相手は両方を塞げない
+    DoubleThreat(Vec<(usize, usize)>),
+    /// 新しくリーチになるマス(index_2d, k)
+    Threat(Vec<(usize, usize)>),
+    /// 相手のリーチの真下を埋めてしまう列。勧める手はそこを避けている。
+    Avoids(Vec<usize>),
+    /// count policyの点数と、置ける手の中での最大
+    Policy { score: usize, best: usize },
+}
+
+/**
+ * ヒントの説明。理由と、エンジンが読んでいる手順。
+ */
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Explanation {
+    pub hand: usize,
+    pub reasons: Vec<Reason>,
+    pub pv: Vec<usize>,
+}
+
+/**
+ * 次にplayerが置けば勝てる、今置けるマス
+ */
+fn playable_threats(board: &BitBoard, player: Player) -> Vec<(usize, usize)> {
+    board
+        .threats(player)
+        .into_iter()
+        .filter(|&(index_2d, k)| board.height(index_2d) == k)
+        .collect()
+}
+
+/**
+ * 相手が一手で両方を塞げないか。二か所にあるか、同じ列に縦に並んでいれば塞げない。
+ */
+fn is_double(board: &BitBoard, player: Player) -> bool {
+    let threats = board.threats(player);
+    let playable = playable_threats(board, player);
+    playable.len() >= 2
+        || playable
+            .iter()
+            .any(|&(index_2d, k)| threats.contains(&(index_2d, k + 1)))
+}
+
+/**
+ * boardでhandを勧める理由を調べる。pvはhandから始まる読み筋。
+ */
+pub fn explain(board: &BitBoard, hand: usize, pv: &[usize]) -> Option<Explanation> {
+    let (after, score) = board.put_with_count_policy(hand)?;
+    let me = board.next_player();
+    let mut reasons = vec![];
+
+    if board.win_index() == Some(hand) {
+        reasons.push(Reason::Win);
+    } else {
+        // 相手のリーチが二つあれば、どちらを塞いでもよい
+        if playable_threats(board, me.next_player())
+            .iter()
+            .any(|&(index_2d, _)| index_2d == hand)
+        {
+            reasons.push(Reason::Block);
+        }
+
+        let before = board.threats(me);
+        let threats = after
+            .threats(me)
+            .into_iter()
+            .filter(|cell| !before.contains(cell))
+            .collect::<Vec<_>>();
+        // 相手にすぐ勝たれるなら両取りにはならない
+        if after.win_index().is_none() && is_double(&after, me) {
+            reasons.push(Reason::DoubleThreat(playable_threats(&after, me)));
+        } else if !threats.is_empty() {
+            reasons.push(Reason::Threat(threats));
+        }
+
+        let opponent = board.threats(me.next_player());
+        let poisoned = (0..N * N)
+            .filter(|&index_2d| {
+                let k = board.height(index_2d);
+                k < N && opponent.contains(&(index_2d, k + 1))
+            })
+            .collect::<Vec<_>>();
+        if !poisoned.is_empty() && !poisoned.contains(&hand) {
+            reasons.push(Reason::Avoids(poisoned));
+        }
+    }
+
+    let best = (0..N * N)
+        .filter_map(|index_2d| board.put_with_count_policy(index_2d))
+        .map(|(_, score)| score)
+        .max()
+        .unwrap_or(0);
+    reasons.push(Reason::Policy { score, best });
+
+    let pv = if pv.first() == Some(&hand) {
+        pv.to_vec()
+    } else {
+        vec![hand]
+    };
+    Some(Explanation { hand, reasons, pv })
+}
+
+/**
+ * 人間の手がすぐに負けにつながる理由
+ */
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Warning {
+    /// 相手のリーチ(check_index)を放っておいている
+    IgnoresCheck(usize),
+    /// 相手のリーチの真下を埋め、そこに置かせてしまう
+    GivesWin(usize),
+    /// 探索で負けが読み切れた。相手の手順。
+    ForcedLoss(Vec<usize>),
+}
+
+/**
+ * handを打つと相手に一手で勝たれるか。探索はしない。
+ */
+pub fn blunder(board: &BitBoard, hand: usize) -> Option<Warning> {
+    let after = board.try_play(hand).ok()?;
+    if after.is_won() {
+        return None;
+    }
+    if let Some(index) = board.check_index() {
+        if index != hand {
+            return Some(Warning::IgnoresCheck(index));
+        }
+    }
+    after.win_index().map(Warning::GivesWin)
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::IgnoresCheck(index) => write!(
+                f,
+                "the opponent threatens to win at {} and this move does not block it",
+                format_column(*index)
+            ),
+            Warning::GivesWin(index) => {
+                write!(f, "this lets the opponent win at {}", format_column(*index))
+            }
+            Warning::ForcedLoss(pv) => write!(
+                f,
+                "the engine sees a forced loss: {}",
+                pv.iter()
+                    .map(|&index| format_column(index))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+        }
+    }
+}
+
+fn format_cells(cells: &[(usize, usize)]) -> String {
+    cells
+        .iter()
+        .map(|&(index_2d, k)| format!("{} on layer {}", format_column(index_2d), k + 1))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Win => write!(f, "completes a line and wins"),
+            Reason::Block => write!(f, "blocks the opponent's immediate win"),
+            Reason::DoubleThreat(cells) => write!(
+                f,
+                "sets up a double threat the opponent cannot stop: {}",
+                format_cells(cells)
+            ),
+            Reason::Threat(cells) => write!(f, "creates a threat at {}", format_cells(cells)),
+            Reason::Avoids(columns) => write!(
+                f,
+                "avoids {}, which would let the opponent play their threat above",
+                columns
+                    .iter()
+                    .map(|&index_2d| format_column(index_2d))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Reason::Policy { score, best } => {
+                write!(f, "line potential {} (best available {})", score, best)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for reason in &self.reasons {
+            writeln!(f, "- {}", reason)?;
+        }
+        let pv = self
+            .pv
+            .iter()
+            .map(|&index_2d| format_column(index_2d))
+            .collect::<Vec<_>>();
+        write!(f, "Expected line: {}", pv.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    fn board(record: &str) -> BitBoard {
+        Game::from_record(record).unwrap().board().clone()
+    }
+
+    #[test]
+    fn test_explain() {
+        // 黒がa1 b1 c1と並べ、d1で勝てる
+        let b = board("a1 a4 b1 b4 c1");
+        let e = explain(&b, 3, &[3]).unwrap();
+        assert_eq!(e.reasons[0], Reason::Block);
+
+        // 黒のリーチがd1とd2の二つあり、どちらを塞いでも受けになる
+        let b = board("a1 a4 b1 b3 c1 d4 a2 c3 b2 a4 c2");
+        for hand in [3, 7] {
+            let e = explain(&b, hand, &[hand]).unwrap();
+            assert_eq!(e.reasons[0], Reason::Block);
+        }
+
+        let b = board("a1 a4 b1 b4 c1 d4");
+        let e = explain(&b, 3, &[3]).unwrap();
+        assert_eq!(e.reasons[0], Reason::Win);
+        assert!(e.to_string().starts_with("- completes a line and wins\n"));
+    }
+
+    #[test]
+    fn test_blunder() {
+        let b = board("a1 a4 b1 b4 c1");
+        assert_eq!(blunder(&b, 0), Some(Warning::IgnoresCheck(3)));
+        assert_eq!(blunder(&b, 3), None);
+
+        // 黒が二段目にa1 b1 c1と並べている。白がd1を埋めると二段目に置かれる。
+        let b = board("a1 b1 b1 c1 c1 d4 a1");
+        assert_eq!(b.check_index(), None);
+        assert_eq!(blunder(&b, 3), Some(Warning::GivesWin(3)));
+        assert_eq!(blunder(&b, 11), None);
+    }
+
+    #[test]
+    fn test_threat() {
+        // 黒がa1 b1と並べた後にc1を置くとd1がリーチ
+        let b = board("a1 a4 b1 b4");
+        let e = explain(&b, 2, &[2, 3]).unwrap();
+        assert!(e.reasons.contains(&Reason::Threat(vec![(3, 0)])));
+        assert!(e.to_string().contains("d1 on layer 1"));
+        assert!(e.to_string().ends_with("Expected line: c1 d1"));
+    }
+}
@@ -0,0 +1,169 @@
+use std::fmt::Write;
+
+use crate::format_column;
+
+/**
+ * 書き出す範囲。根からdepth手まで、試行回数がmin_visits以上のノードだけをたどる。
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct ExportOptions {
+    pub depth: usize,
+    pub min_visits: usize,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            depth: 2,
+            min_visits: 1,
+        }
+    }
+}
+
+/**
+ * 書き出し用に木から取り出したノード。勝率はそのノードへ打った側から見る。
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    /// 根ならNone
+    pub hand: Option<usize>,
+    pub visits: usize,
+    pub win_rate: Option<f32>,
+    pub prior: Option<usize>,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    /**
+     * 打った側から見たwin, drawの回数から作る
+     */
+    pub fn new(hand: usize, visits: usize, win: usize, draw: usize, prior: Option<usize>) -> Self {
+        let win_rate = (visits > 0).then(|| (win as f32 + draw as f32 / 2.0) / visits as f32);
+        Node {
+            hand: Some(hand),
+            visits,
+            win_rate,
+            prior,
+            children: vec![],
+        }
+    }
+
+    pub fn root(children: Vec<Node>) -> Self {
+        Node {
+            hand: None,
+            visits: children.iter().map(|c| c.visits).sum(),
+            win_rate: None,
+            prior: None,
+            children,
+        }
+    }
+
+    fn label(&self) -> String {
+        let mut label = match self.hand {
+            Some(hand) => format_column(hand),
+            None => "root".to_owned(),
+        };
+        write!(label, "\\nvisits {}", self.visits).unwrap();
+        if let Some(rate) = self.win_rate {
+            write!(label, "\\nwin {:.1}%", rate * 100f32).unwrap();
+        }
+        if let Some(prior) = self.prior {
+            write!(label, "\\nprior {}", prior).unwrap();
+        }
+        label
+    }
+
+    /**
+     * Graphvizのdot形式
+     */
+    pub fn to_dot(&self) -> String {
+        fn walk(node: &Node, id: &mut usize, out: &mut String) -> usize {
+            let me = *id;
+            *id += 1;
+            writeln!(out, "  n{} [label=\"{}\"];", me, node.label()).unwrap();
+            for child in &node.children {
+                let child_id = walk(child, id, out);
+                writeln!(out, "  n{} -> n{};", me, child_id).unwrap();
+            }
+            me
+        }
+
+        let mut out = "digraph tree {\n  node [shape=box];\n".to_owned();
+        walk(self, &mut 0, &mut out);
+        out.push_str("}\n");
+        out
+    }
+
+    /**
+     * 入れ子のJSON
+     */
+    pub fn to_json(&self) -> String {
+        let hand = match self.hand {
+            Some(hand) => format!("\"{}\"", format_column(hand)),
+            None => "null".to_owned(),
+        };
+        let win_rate = match self.win_rate {
+            Some(rate) => format!("{:.4}", rate),
+            None => "null".to_owned(),
+        };
+        let prior = match self.prior {
+            Some(prior) => prior.to_string(),
+            None => "null".to_owned(),
+        };
+        let children = self
+            .children
+            .iter()
+            .map(Node::to_json)
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            "{{\"move\":{},\"visits\":{},\"win_rate\":{},\"prior\":{},\"children\":[{}]}}",
+            hand, self.visits, win_rate, prior, children
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bitboard::BitBoard,
+        mctree,
+        search::SearchLimits,
+        simple_puct::{self, SimplePolicy},
+        N,
+    };
+
+    #[test]
+    fn test_export() {
+        let mut tree = simple_puct::McTreeRoot::<SimplePolicy>::new(BitBoard::new());
+        tree.search(&SearchLimits::playouts(50));
+        let node = tree.export(&ExportOptions {
+            depth: 2,
+            min_visits: 10,
+        });
+        assert_eq!(node.children.len(), N * N);
+        assert!(node
+            .children
+            .iter()
+            .flat_map(|c| &c.children)
+            .all(|c| c.visits >= 10 && c.children.is_empty()));
+
+        let dot = node.to_dot();
+        assert!(dot.starts_with("digraph tree {"));
+        assert!(dot.contains("n0 -> n1;"));
+        assert!(dot.contains("n1 [label=\"a1\\nvisits "));
+
+        let mut tree = mctree::McTreeRoot::new(BitBoard::new());
+        tree.search(&SearchLimits::playouts(30));
+        let node = tree.export(&ExportOptions {
+            depth: 1,
+            min_visits: 1,
+        });
+        assert!(node.children.iter().all(|c| c.children.is_empty()));
+        let json = node.to_json();
+        assert!(json.starts_with("{\"move\":null,\"visits\":"));
+        assert!(json.contains("{\"move\":\"d4\",\"visits\":"));
+        assert!(json.ends_with("\"children\":[]}]}"));
+    }
+}
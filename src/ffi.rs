@@ -0,0 +1,475 @@
+//! C言語から使うためのAPI。ヘッダーはinclude/yonmoku.hで、次のように作り直す。
+//!
+//! ```text
+//! cbindgen --config cbindgen.toml --output include/yonmoku.h src/ffi.rs
+//! ```
+//!
+//! 列は0から15の番号(i * 4 + j)で指定する。盤はyonmoku_board_newで作り、
+//! yonmoku_board_freeで捨てる。関数は渡されたポインタを持ち続けない。
+use std::{
+    ffi::{c_char, CStr},
+    ptr,
+    time::Duration,
+};
+
+use crate::{
+    bitboard::BitBoard,
+    board::{MoveError, Player},
+    mctree,
+    search::{new_seed, Proof, SearchLimits, SearchResult},
+    simple_puct::{self, CountPolicy, SimplePolicy},
+    N,
+};
+
+/// 盤の列の数
+pub const YONMOKU_COLUMNS: usize = 16;
+const _: () = assert!(YONMOKU_COLUMNS == N * N);
+
+/// PUCTとsimple policy。既定の木。
+pub const YONMOKU_TREE_PUCT_SIMPLE: u32 = 0;
+/// PUCTとcount policy
+pub const YONMOKU_TREE_PUCT_COUNT: u32 = 1;
+/// UCB1
+pub const YONMOKU_TREE_UCB1: u32 = 2;
+
+/// 盤。中身はC側からは見えない。
+pub struct YonmokuBoard(BitBoard);
+
+/// 関数の結果
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum YonmokuStatus {
+    Ok = 0,
+    /// ポインタがNULLか、引数が正しくない
+    InvalidArgument = 1,
+    /// 列の番号が範囲外
+    OutOfRange = 2,
+    /// 列が埋まっている
+    ColumnFull = 3,
+    /// 終局している
+    GameOver = 4,
+}
+
+/// 局面の状態
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum YonmokuOutcome {
+    Ongoing = 0,
+    BlackWin = 1,
+    WhiteWin = 2,
+    Draw = 3,
+}
+
+/// 読み切りの結果。手番側から見る。
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum YonmokuProof {
+    Unknown = 0,
+    Win = 1,
+    Lose = 2,
+    Draw = 3,
+}
+
+/// 探索の打ち切り条件。0の項目は使わない。playoutsとtime_msが両方0なら1000プレイアウト。
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct YonmokuLimits {
+    /// 候補手あたりの試行回数
+    pub playouts: u64,
+    pub time_ms: u64,
+    /// 0なら適当に決める
+    pub seed: u64,
+    /// YONMOKU_TREE_*
+    pub tree: u32,
+}
+
+/// 候補手ごとの統計。勝ち負けは探索した局面の手番側から見る。
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct YonmokuMoveStats {
+    pub column: u32,
+    pub visits: u64,
+    pub win: u64,
+    pub draw: u64,
+    pub lose: u64,
+    pub win_rate: f32,
+    /// policyの点数。なければ-1
+    pub prior: i32,
+    pub proven: YonmokuProof,
+}
+
+/// 探索結果。movesの先頭n_moves個が強い順に並ぶ。
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct YonmokuSearchResult {
+    /// 最善手の列。置けなければ-1
+    pub best: i32,
+    /// 王手の応手しか調べていない
+    pub forced: bool,
+    /// 使ったseed
+    pub seed: u64,
+    pub n_moves: u32,
+    pub moves: [YonmokuMoveStats; YONMOKU_COLUMNS],
+}
+
+/// 初期局面の盤を作る
+#[no_mangle]
+pub extern "C" fn yonmoku_board_new() -> *mut YonmokuBoard {
+    Box::into_raw(Box::new(YonmokuBoard(BitBoard::new())))
+}
+
+/// 局面文字列から盤を作る。読めなければNULL。
+///
+/// # Safety
+/// positionはNUL終端の文字列か、NULL。
+#[no_mangle]
+pub unsafe extern "C" fn yonmoku_board_from_position(position: *const c_char) -> *mut YonmokuBoard {
+    if position.is_null() {
+        return ptr::null_mut();
+    }
+    CStr::from_ptr(position)
+        .to_str()
+        .ok()
+        .and_then(BitBoard::from_position_string)
+        .map_or(ptr::null_mut(), |board| {
+            Box::into_raw(Box::new(YonmokuBoard(board)))
+        })
+}
+
+/// 盤を複製する。boardがNULLならNULL。
+///
+/// # Safety
+/// boardはyonmoku_board_*で作った盤か、NULL。
+#[no_mangle]
+pub unsafe extern "C" fn yonmoku_board_clone(board: *const YonmokuBoard) -> *mut YonmokuBoard {
+    match board.as_ref() {
+        Some(board) => Box::into_raw(Box::new(YonmokuBoard(board.0.clone()))),
+        None => ptr::null_mut(),
+    }
+}
+
+/// 盤を捨てる。NULLなら何もしない。
+///
+/// # Safety
+/// boardはyonmoku_board_*で作った盤か、NULL。二度捨ててはいけない。
+#[no_mangle]
+pub unsafe extern "C" fn yonmoku_board_free(board: *mut YonmokuBoard) {
+    if !board.is_null() {
+        drop(Box::from_raw(board));
+    }
+}
+
+/// columnに置く。置けなければ盤は変わらない。
+///
+/// # Safety
+/// boardはyonmoku_board_*で作った盤か、NULL。
+#[no_mangle]
+pub unsafe extern "C" fn yonmoku_board_play(
+    board: *mut YonmokuBoard,
+    column: u32,
+) -> YonmokuStatus {
+    let Some(board) = board.as_mut() else {
+        return YonmokuStatus::InvalidArgument;
+    };
+    match board.0.try_play(column as usize) {
+        Ok(next) => {
+            board.0 = next;
+            YonmokuStatus::Ok
+        }
+        Err(MoveError::OutOfRange(_)) => YonmokuStatus::OutOfRange,
+        Err(MoveError::ColumnFull(_)) => YonmokuStatus::ColumnFull,
+        Err(MoveError::GameOver) => YonmokuStatus::GameOver,
+    }
+}
+
+/// 置ける列をmovesに書き、その数を返す。終局していれば0。
+/// movesはYONMOKU_COLUMNS個あれば足りる。lenより多い分は書かずに数だけ数える。
+///
+/// # Safety
+/// boardはyonmoku_board_*で作った盤か、NULL。movesはlen個書ける領域か、lenが0ならNULLでもよい。
+#[no_mangle]
+pub unsafe extern "C" fn yonmoku_board_legal_moves(
+    board: *const YonmokuBoard,
+    moves: *mut u32,
+    len: usize,
+) -> usize {
+    let Some(board) = board.as_ref() else {
+        return 0;
+    };
+    if board.0.is_won() || board.0.is_full() {
+        return 0;
+    }
+    let mut n = 0;
+    for column in (0..N * N).filter(|&column| board.0.height(column) < N) {
+        if n < len && !moves.is_null() {
+            *moves.add(n) = column as u32;
+        }
+        n += 1;
+    }
+    n
+}
+
+/// 勝敗が決まったか
+///
+/// # Safety
+/// boardはyonmoku_board_*で作った盤。
+#[no_mangle]
+pub unsafe extern "C" fn yonmoku_board_outcome(board: *const YonmokuBoard) -> YonmokuOutcome {
+    let Some(board) = board.as_ref() else {
+        return YonmokuOutcome::Ongoing;
+    };
+    if board.0.is_won() {
+        match board.0.next_player() {
+            Player::White => YonmokuOutcome::BlackWin,
+            Player::Black => YonmokuOutcome::WhiteWin,
+        }
+    } else if board.0.is_full() {
+        YonmokuOutcome::Draw
+    } else {
+        YonmokuOutcome::Ongoing
+    }
+}
+
+/// 手番。黒なら1、白なら2。
+///
+/// # Safety
+/// boardはyonmoku_board_*で作った盤。
+#[no_mangle]
+pub unsafe extern "C" fn yonmoku_board_side_to_move(board: *const YonmokuBoard) -> i32 {
+    match board.as_ref().map(|board| board.0.next_player()) {
+        Some(Player::Black) => 1,
+        Some(Player::White) => 2,
+        None => 0,
+    }
+}
+
+/// 手番側が置けば勝てる列。なければ-1。
+///
+/// # Safety
+/// boardはyonmoku_board_*で作った盤。
+#[no_mangle]
+pub unsafe extern "C" fn yonmoku_board_win_column(board: *const YonmokuBoard) -> i32 {
+    board
+        .as_ref()
+        .and_then(|board| board.0.win_index())
+        .map_or(-1, |column| column as i32)
+}
+
+/// 相手が次に勝てるので、手番側が塞がなければいけない列。なければ-1。
+///
+/// # Safety
+/// boardはyonmoku_board_*で作った盤。
+#[no_mangle]
+pub unsafe extern "C" fn yonmoku_board_check_column(board: *const YonmokuBoard) -> i32 {
+    board
+        .as_ref()
+        .and_then(|board| board.0.check_index())
+        .map_or(-1, |column| column as i32)
+}
+
+/// 局面文字列をbufにNUL終端で書く。終端を除いた長さを返す。
+/// lenが足りなければ書ける分だけ書くので、戻り値+1の大きさで呼び直す。
+///
+/// # Safety
+/// boardはyonmoku_board_*で作った盤。bufはlen個書ける領域か、lenが0ならNULLでもよい。
+#[no_mangle]
+pub unsafe extern "C" fn yonmoku_board_position(
+    board: *const YonmokuBoard,
+    buf: *mut c_char,
+    len: usize,
+) -> usize {
+    let Some(board) = board.as_ref() else {
+        return 0;
+    };
+    let position = board.0.position_string();
+    if len > 0 && !buf.is_null() {
+        let n = position.len().min(len - 1);
+        ptr::copy_nonoverlapping(position.as_ptr().cast::<c_char>(), buf, n);
+        *buf.add(n) = 0;
+    }
+    position.len()
+}
+
+fn proof(proof: Option<Proof>) -> YonmokuProof {
+    match proof {
+        Some(Proof::Win) => YonmokuProof::Win,
+        Some(Proof::Lose) => YonmokuProof::Lose,
+        Some(Proof::Draw) => YonmokuProof::Draw,
+        None => YonmokuProof::Unknown,
+    }
+}
+
+fn search(board: &BitBoard, limits: &YonmokuLimits, seed: u64) -> SearchResult {
+    let mut search_limits = SearchLimits::default();
+    if limits.playouts > 0 {
+        search_limits.playouts = Some(limits.playouts as usize);
+    }
+    if limits.time_ms > 0 {
+        search_limits.time = Some(Duration::from_millis(limits.time_ms));
+    }
+    if search_limits.is_infinite() {
+        search_limits.playouts = Some(1_000);
+    }
+
+    let board = board.clone();
+    match limits.tree {
+        YONMOKU_TREE_UCB1 => {
+            let mut tree = mctree::McTreeRoot::new(board);
+            tree.set_seed(seed);
+            tree.search(&search_limits)
+        }
+        YONMOKU_TREE_PUCT_COUNT => {
+            let mut tree = simple_puct::McTreeRoot::<CountPolicy>::new(board);
+            tree.set_seed(seed);
+            tree.search(&search_limits)
+        }
+        _ => {
+            let mut tree = simple_puct::McTreeRoot::<SimplePolicy>::new(board);
+            tree.set_seed(seed);
+            tree.search(&search_limits)
+        }
+    }
+}
+
+/// 探索して、最善手と候補手ごとの統計をresultに書く。終局していればbestは-1。
+///
+/// # Safety
+/// boardはyonmoku_board_*で作った盤。limitsとresultは有効な領域。
+#[no_mangle]
+pub unsafe extern "C" fn yonmoku_search(
+    board: *const YonmokuBoard,
+    limits: *const YonmokuLimits,
+    result: *mut YonmokuSearchResult,
+) -> YonmokuStatus {
+    let (Some(board), Some(limits), Some(result)) =
+        (board.as_ref(), limits.as_ref(), result.as_mut())
+    else {
+        return YonmokuStatus::InvalidArgument;
+    };
+    if limits.tree > YONMOKU_TREE_UCB1 {
+        return YonmokuStatus::InvalidArgument;
+    }
+    let seed = if limits.seed == 0 {
+        new_seed()
+    } else {
+        limits.seed
+    };
+
+    let empty = YonmokuMoveStats {
+        column: 0,
+        visits: 0,
+        win: 0,
+        draw: 0,
+        lose: 0,
+        win_rate: 0.0,
+        prior: -1,
+        proven: YonmokuProof::Unknown,
+    };
+    *result = YonmokuSearchResult {
+        best: -1,
+        forced: false,
+        seed,
+        n_moves: 0,
+        moves: [empty; YONMOKU_COLUMNS],
+    };
+    if board.0.is_won() || board.0.is_full() {
+        return YonmokuStatus::GameOver;
+    }
+
+    let found = search(&board.0, limits, seed);
+    result.best = found.best().map_or(-1, |m| m.hand as i32);
+    result.forced = found.forced;
+    result.n_moves = found.moves.len() as u32;
+    for (stats, m) in result.moves.iter_mut().zip(&found.moves) {
+        *stats = YonmokuMoveStats {
+            column: m.hand as u32,
+            visits: m.visits as u64,
+            win: m.win as u64,
+            draw: m.draw as u64,
+            lose: m.lose as u64,
+            win_rate: m.win_rate(),
+            prior: m.prior.map_or(-1, |p| p as i32),
+            proven: proof(m.proven),
+        };
+    }
+    YonmokuStatus::Ok
+}
+
+/// ライブラリのバージョン。NUL終端の静的な文字列。
+#[no_mangle]
+pub extern "C" fn yonmoku_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi() {
+        unsafe {
+            let board = yonmoku_board_new();
+            // 黒がa1 b1 c1、白がa4 b4と置くと、白はd1を塞ぐしかない
+            for column in [0, 12, 1, 13, 2] {
+                assert_eq!(yonmoku_board_play(board, column), YonmokuStatus::Ok);
+            }
+            assert_eq!(yonmoku_board_side_to_move(board), 2);
+            assert_eq!(yonmoku_board_check_column(board), 3);
+            assert_eq!(yonmoku_board_win_column(board), -1);
+            assert_eq!(yonmoku_board_play(board, 16), YonmokuStatus::OutOfRange);
+
+            let mut moves = [0u32; YONMOKU_COLUMNS];
+            assert_eq!(
+                yonmoku_board_legal_moves(board, moves.as_mut_ptr(), moves.len()),
+                16
+            );
+
+            let limits = YonmokuLimits {
+                playouts: 10,
+                time_ms: 0,
+                seed: 1,
+                tree: YONMOKU_TREE_PUCT_SIMPLE,
+            };
+            let mut result = std::mem::zeroed::<YonmokuSearchResult>();
+            assert_eq!(
+                yonmoku_search(board, &limits, &mut result),
+                YonmokuStatus::Ok
+            );
+            assert!(result.forced);
+            assert_eq!(result.best, 3);
+            assert_eq!(result.n_moves, 1);
+
+            let mut buf = [0 as c_char; 64];
+            let len = yonmoku_board_position(board, buf.as_mut_ptr(), buf.len());
+            let copy = yonmoku_board_from_position(buf.as_ptr());
+            assert_eq!((*copy).0, (*board).0);
+            assert_eq!(len, (*board).0.position_string().len());
+
+            // 白が塞がなければ黒がd1で勝つ
+            assert_eq!(yonmoku_board_play(copy, 14), YonmokuStatus::Ok);
+            assert_eq!(yonmoku_board_play(copy, 3), YonmokuStatus::Ok);
+            assert_eq!(yonmoku_board_outcome(copy), YonmokuOutcome::BlackWin);
+            assert_eq!(yonmoku_board_play(copy, 4), YonmokuStatus::GameOver);
+            assert_eq!(yonmoku_board_legal_moves(copy, ptr::null_mut(), 0), 0);
+
+            yonmoku_board_free(copy);
+            yonmoku_board_free(board);
+        }
+    }
+
+    #[test]
+    fn test_header() {
+        // 関数を足したらヘッダーも作り直す
+        let header = include_str!("../include/yonmoku.h");
+        for line in include_str!("ffi.rs").lines() {
+            if let Some((_, rest)) = line.split_once("extern \"C\" fn yonmoku_") {
+                let name = rest.split('(').next().unwrap();
+                assert!(
+                    header.contains(&format!("yonmoku_{}(", name)),
+                    "yonmoku_{} is not in the header",
+                    name
+                );
+            }
+        }
+    }
+}
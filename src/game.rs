@@ -0,0 +1,260 @@
+use std::{fs, io, path::Path};
+
+use crate::{
+    bitboard::BitBoard,
+    board::{MoveError, Player},
+    format_column, parse_column, N,
+};
+
+/**
+ * 終局の結果
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    Win(Player),
+    Draw,
+}
+
+/**
+ * 一局分の棋譜。待ったと戻したのやり直しができる。
+ *
+ * 保存形式は"a1 b2 c3"のように手を空白区切りで並べた一行。
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Game {
+    boards: Vec<BitBoard>,
+    moves: Vec<usize>,
+    /// 待ったで戻した手。最後に戻した手が末尾。
+    redo: Vec<usize>,
+}
+
+impl Game {
+    pub fn new() -> Self {
+        Game {
+            boards: vec![BitBoard::new()],
+            moves: vec![],
+            redo: vec![],
+        }
+    }
+
+    pub fn board(&self) -> &BitBoard {
+        self.boards.last().unwrap()
+    }
+
+    pub fn moves(&self) -> &[usize] {
+        &self.moves
+    }
+
+    pub fn last_move(&self) -> Option<usize> {
+        self.moves.last().copied()
+    }
+
+    /**
+     * これまでに置いた石の数
+     */
+    pub fn ply(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn side_to_move(&self) -> Player {
+        self.board().next_player()
+    }
+
+    /**
+     * 置ける列。終局していれば空。
+     */
+    pub fn legal_moves(&self) -> Vec<usize> {
+        if self.is_over() {
+            return vec![];
+        }
+        (0..N * N)
+            .filter(|&index| self.board().height(index) < N)
+            .collect()
+    }
+
+    /**
+     * 実際にラインが完成したか、盤が埋まったときだけ結果を返す
+     */
+    pub fn result(&self) -> Option<GameResult> {
+        let board = self.board();
+        if board.is_won() {
+            Some(GameResult::Win(board.next_player().next_player()))
+        } else if board.is_full() {
+            Some(GameResult::Draw)
+        } else {
+            None
+        }
+    }
+
+    /**
+     * 完成したラインのマス(index_2d, k)
+     */
+    pub fn winning_line(&self) -> Option<Vec<(usize, usize)>> {
+        self.board().winning_line()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.result().is_some()
+    }
+
+    /**
+     * 終局まで打ち進める。chooseは手番側の手を返す。
+     */
+    pub fn play_out(
+        &mut self,
+        mut choose: impl FnMut(&Game) -> usize,
+    ) -> Result<GameResult, MoveError> {
+        loop {
+            if let Some(result) = self.result() {
+                return Ok(result);
+            }
+            let index = choose(self);
+            self.play(index)?;
+        }
+    }
+
+    /**
+     * indexに置く。やり直しの手は捨てる。
+     */
+    pub fn play(&mut self, index: usize) -> Result<&BitBoard, MoveError> {
+        let board = self.board().try_play(index)?;
+        self.boards.push(board);
+        self.moves.push(index);
+        self.redo.clear();
+        Ok(self.board())
+    }
+
+    /**
+     * 一手戻す。戻した手を返す。
+     */
+    pub fn undo(&mut self) -> Option<usize> {
+        let index = self.moves.pop()?;
+        self.boards.pop();
+        self.redo.push(index);
+        Some(index)
+    }
+
+    /**
+     * 待ったした手を一手進める
+     */
+    pub fn redo(&mut self) -> Option<usize> {
+        let index = self.redo.pop()?;
+        let board = self.board().put(index).unwrap();
+        self.boards.push(board);
+        self.moves.push(index);
+        Some(index)
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn to_record(&self) -> String {
+        self.moves
+            .iter()
+            .map(|&index| format_column(index))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn from_record(record: &str) -> Result<Self, String> {
+        let mut game = Game::new();
+        for (n, token) in record.split_whitespace().enumerate() {
+            let index = parse_column(token).ok_or_else(|| format!("invalid move: {}", token))?;
+            game.play(index)
+                .map_err(|e| format!("illegal move {} at ply {}: {}", token, n + 1, e))?;
+        }
+        Ok(game)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_record() + "\n")
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let record = fs::read_to_string(path)?;
+        Game::from_record(&record).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::N;
+
+    #[test]
+    fn test_column() {
+        assert_eq!(format_column(0), "a1");
+        assert_eq!(format_column(N + 2), "c2");
+        for index in 0..N * N {
+            assert_eq!(parse_column(&format_column(index)), Some(index));
+        }
+        assert_eq!(parse_column("B3"), Some(2 * N + 1));
+        assert_eq!(parse_column("2,1"), Some(2 * N + 1));
+        assert_eq!(parse_column("e1"), None);
+        assert_eq!(parse_column("a0"), None);
+        assert_eq!(parse_column("a5"), None);
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut game = Game::new();
+        for index in [0, 0, 0, 0] {
+            assert!(game.play(index).is_ok());
+        }
+        assert_eq!(game.play(0).err(), Some(MoveError::ColumnFull(0)));
+
+        assert_eq!(game.undo(), Some(0));
+        assert_eq!(game.undo(), Some(0));
+        assert_eq!(game.ply(), 2);
+        assert_eq!(game.redo(), Some(0));
+        assert_eq!(game.ply(), 3);
+
+        // 新しい手を打てばやり直しは消える
+        game.play(5).unwrap();
+        assert!(!game.can_redo());
+        assert_eq!(game.to_record(), "a1 a1 a1 b2");
+    }
+
+    #[test]
+    fn test_result() {
+        // 白は黒の三つ目でリーチをかけられても、実際に並ぶまでは終局しない
+        let mut game = Game::from_record("a1 a4 b1 b4 c1").unwrap();
+        assert_eq!(game.result(), None);
+        assert_eq!(game.side_to_move(), Player::White);
+        assert_eq!(game.legal_moves().len(), N * N);
+
+        game.play(15).unwrap();
+        game.play(3).unwrap();
+        assert_eq!(game.result(), Some(GameResult::Win(Player::Black)));
+        assert_eq!(
+            game.winning_line(),
+            Some(vec![(0, 0), (1, 0), (2, 0), (3, 0)])
+        );
+        assert!(game.legal_moves().is_empty());
+        assert_eq!(game.play(4).err(), Some(MoveError::GameOver));
+
+        // 全部埋めれば引き分けか勝ちで終わる
+        let mut game = Game::new();
+        let result = game.play_out(|g| g.legal_moves()[0]).unwrap();
+        assert_eq!(game.result(), Some(result));
+    }
+
+    #[test]
+    fn test_record() {
+        let game = Game::from_record("a1 b2 a1\n").unwrap();
+        assert_eq!(game.moves(), &[0, 5, 0]);
+        assert_eq!(
+            Game::from_record(&game.to_record()).unwrap().board(),
+            game.board()
+        );
+
+        assert!(Game::from_record("a1 x9").is_err());
+        assert!(Game::from_record("a1 a1 a1 a1 a1").is_err());
+
+        let path = std::env::temp_dir().join(format!("yonmoku-test-{}.txt", std::process::id()));
+        game.save(&path).unwrap();
+        assert_eq!(Game::load(&path).unwrap().moves(), game.moves());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,202 @@
+use std::{fmt, str::FromStr};
+
+use rand::Rng;
+
+use crate::search::SearchResult;
+
+/**
+ * CPUの強さ。
+ *
+ * 探索の試行回数と、根の勝率に対するsoftmaxの温度、わざと適当に打つ確率(blunder)を決める。
+ * 王手への応手は探索の時点で一手に絞られるので、どの強さでも必ず受ける。
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Level {
+    Easy,
+    Normal,
+    Hard,
+    #[default]
+    Max,
+}
+
+impl Level {
+    pub const ALL: [Level; 4] = [Level::Easy, Level::Normal, Level::Hard, Level::Max];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Easy => "easy",
+            Level::Normal => "normal",
+            Level::Hard => "hard",
+            Level::Max => "max",
+        }
+    }
+
+    /**
+     * 一つ上の強さ。Maxの次はEasyに戻る。
+     */
+    pub fn next(self) -> Self {
+        let index = Level::ALL.iter().position(|&l| l == self).unwrap();
+        Level::ALL[(index + 1) % Level::ALL.len()]
+    }
+
+    /**
+     * stone手目で使う候補手あたりの試行回数
+     */
+    pub fn playouts(self, stone: usize) -> usize {
+        let n_try = match self {
+            Level::Easy => 2_000,
+            Level::Normal => 20_000,
+            Level::Hard => 100_000,
+            Level::Max => 200_000,
+        };
+        n_try * (1 + stone * stone / 50)
+    }
+
+    /**
+     * softmaxの温度。0なら常に最善手。
+     */
+    pub fn temperature(self) -> f32 {
+        match self {
+            Level::Easy => 0.1,
+            Level::Normal => 0.03,
+            Level::Hard => 0.01,
+            Level::Max => 0.0,
+        }
+    }
+
+    /**
+     * 候補手から一様に選んでしまう確率
+     */
+    pub fn blunder(self) -> f32 {
+        match self {
+            Level::Easy => 0.2,
+            Level::Normal => 0.05,
+            Level::Hard | Level::Max => 0.0,
+        }
+    }
+
+    /**
+     * 相手の手番に先読みするか。弱いレベルで読みが増えると強くなりすぎる。
+     */
+    pub fn ponder(self) -> bool {
+        matches!(self, Level::Hard | Level::Max)
+    }
+
+    pub fn style(self) -> Style {
+        Style {
+            temperature: self.temperature(),
+            blunder: self.blunder(),
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Level::ALL
+            .into_iter()
+            .find(|l| l.name().eq_ignore_ascii_case(s) || l.name()[..1].eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown level: {} (easy, normal, hard, max)", s))
+    }
+}
+
+/**
+ * 探索結果から手を選ぶときの崩し方
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Style {
+    pub temperature: f32,
+    pub blunder: f32,
+}
+
+impl Style {
+    /**
+     * return (hand, eval)。evalは相手から見た勝率。
+     */
+    pub fn choose(&self, result: &SearchResult, rng: &mut impl Rng) -> Option<(usize, f32)> {
+        let moves = &result.moves;
+        if result.forced || moves.len() <= 1 {
+            return result.select();
+        }
+
+        let m = if rng.gen::<f32>() < self.blunder {
+            &moves[rng.gen_range(0..moves.len())]
+        } else if self.temperature > 0.0 {
+            // 最善手との差でsoftmaxをとる
+            let best = moves[0].win_rate();
+            let weights = moves
+                .iter()
+                .map(|m| ((m.win_rate() - best) / self.temperature).exp())
+                .collect::<Vec<_>>();
+            let mut x = rng.gen::<f32>() * weights.iter().sum::<f32>();
+            let mut chosen = &moves[0];
+            for (m, w) in moves.iter().zip(&weights) {
+                chosen = m;
+                if x < *w {
+                    break;
+                }
+                x -= w;
+            }
+            chosen
+        } else {
+            &moves[0]
+        };
+        Some((m.hand, 1f32 - m.win_rate()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::MoveStats;
+
+    fn stats(hand: usize, win: usize) -> MoveStats {
+        MoveStats {
+            hand,
+            visits: 100,
+            win,
+            draw: 0,
+            lose: 100 - win,
+            prior: None,
+            pv: vec![hand],
+            proven: None,
+        }
+    }
+
+    #[test]
+    fn test_level() {
+        assert_eq!("Easy".parse::<Level>(), Ok(Level::Easy));
+        assert_eq!("n".parse::<Level>(), Ok(Level::Normal));
+        assert!("hardest".parse::<Level>().is_err());
+        assert_eq!(Level::Max.next(), Level::Easy);
+        assert!(Level::Easy.playouts(0) < Level::Max.playouts(0));
+    }
+
+    #[test]
+    fn test_choose() {
+        let mut rng = rand::thread_rng();
+        let result = SearchResult::new(vec![stats(0, 30), stats(1, 60), stats(2, 50)], false);
+        assert_eq!(Level::Max.style().choose(&result, &mut rng).unwrap().0, 1);
+
+        // 必ず適当に打つ
+        let style = Style {
+            temperature: 0.0,
+            blunder: 1.0,
+        };
+        let chosen = (0..200)
+            .map(|_| style.choose(&result, &mut rng).unwrap().0)
+            .collect::<Vec<_>>();
+        assert!([0, 1, 2].iter().all(|hand| chosen.contains(hand)));
+
+        // 王手の応手はそのまま
+        let forced = SearchResult::new(vec![stats(3, 10)], true);
+        assert_eq!(style.choose(&forced, &mut rng), Some((3, 0.9)));
+    }
+}
@@ -1,11 +1,77 @@
 pub const N: usize = 4;
 
+pub mod arena;
 pub mod bitboard;
 pub mod board;
+pub mod dfpn;
+pub mod explain;
+pub mod export;
+pub mod ffi;
+pub mod game;
+pub mod level;
 pub mod mctree;
 pub mod mctree_old;
+pub mod perft;
+pub mod ponder;
+pub mod proof;
+pub mod protocol;
+pub mod puzzle;
+pub mod render;
+pub mod search;
 pub mod simple_puct;
+pub mod solver;
 
 pub fn unpack_index(index: usize) -> (usize, usize) {
     (index / N, index % N)
 }
+
+/**
+ * "i,j"
+ */
+pub fn format_index(index: usize) -> String {
+    let (i, j) = unpack_index(index);
+    format!("{},{}", i, j)
+}
+
+pub fn parse_index(input: &str) -> Option<usize> {
+    let (i, j) = input.trim().split_once(',')?;
+    let (i, j) = (
+        i.trim().parse::<usize>().ok()?,
+        j.trim().parse::<usize>().ok()?,
+    );
+    if i < N && j < N {
+        Some(i * N + j)
+    } else {
+        None
+    }
+}
+
+/**
+ * "b3"。英字がj(a=0)、数字がi+1。
+ */
+pub fn format_column(index: usize) -> String {
+    let (i, j) = unpack_index(index);
+    format!("{}{}", (b'a' + j as u8) as char, i + 1)
+}
+
+/**
+ * "b3"と"i,j"のどちらでも受け付ける
+ */
+pub fn parse_column(input: &str) -> Option<usize> {
+    let input = input.trim();
+    if input.contains(',') {
+        return parse_index(input);
+    }
+    let mut chars = input.chars();
+    let j = chars.next()?.to_ascii_lowercase();
+    let i = chars.as_str().parse::<usize>().ok()?;
+    if !j.is_ascii_lowercase() || i == 0 {
+        return None;
+    }
+    let j = j as usize - 'a' as usize;
+    if i <= N && j < N {
+        Some((i - 1) * N + j)
+    } else {
+        None
+    }
+}
@@ -1,183 +1,479 @@
-use crate::{bitboard::BitBoard, N};
-use rand::random;
+use std::time::Instant;
+
+use crate::{
+    arena::{self, Arena, MemoryLimit, NodeId},
+    bitboard::BitBoard,
+    export::{self, ExportOptions},
+    search::{
+        new_seed, rng_stream, MoveStats, Proof, SearchLimits, SearchResult, SearchRng, SearchStats,
+    },
+    N,
+};
+use rand::Rng;
 use rayon::prelude::*;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct McTreeRoot {
     current_board: BitBoard,
     leaves: [Option<McTreeLeaf>; N * N],
+    memory: MemoryLimit,
+    seed: u64,
+    /// これまでに探索した回数。乱数列を分けるのに使う。
+    rounds: u64,
 }
 
+/**
+ * 根の候補手ひとつ分の部分木
+ */
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct McTreeLeaf {
     current_board: BitBoard,
-    n_trial: usize,
-    n_win: usize,
-    n_lose: usize,
-    leaves: Option<[Option<Box<McTreeLeaf>>; N * N]>,
+    arena: Arena,
 }
 
 #[derive(PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum McResult {
     Win,
     Lose,
     Draw,
 }
 
+impl McResult {
+    /**
+     * return (try, win, lose)
+     */
+    fn counts(&self) -> (usize, usize, usize) {
+        match self {
+            McResult::Win => (1, 1, 0),
+            McResult::Lose => (1, 0, 1),
+            McResult::Draw => (1, 0, 0),
+        }
+    }
+}
+
 impl McTreeLeaf {
-    pub fn new(board: BitBoard) -> Self {
+    fn new(board: BitBoard, hand: usize, memory: &MemoryLimit) -> Self {
         McTreeLeaf {
             current_board: board,
-            n_trial: 0,
-            n_win: 0,
-            n_lose: 0,
-            leaves: None,
+            arena: Arena::new(arena::Node::new(hand, 0), memory),
         }
     }
 
-    fn win_rate(&self) -> f32 {
-        (self.n_win as f32 + ((self.n_trial - self.n_win - self.n_lose) as f32) / 2.0)
-            / (self.n_trial as f32)
+    fn node(&self) -> &arena::Node {
+        self.arena.root()
     }
 
-    pub fn select_rate(&self, n_try: usize) -> f32 {
-        let c = 1.4f32;
-        (1f32 - self.win_rate()) + c * ((n_try as f32).ln() / self.n_trial as f32).sqrt()
+    /**
+     * 手番側から見た読み切りの結果
+     */
+    fn proven(&self, id: NodeId, board: &BitBoard) -> Option<Proof> {
+        if board.win_index().is_some() {
+            Some(Proof::Win)
+        } else if board.is_full() {
+            Some(Proof::Draw)
+        } else if !self.arena.get(id).is_expanded() {
+            None
+        } else {
+            Proof::from_children(self.arena.children(id).map(|child| {
+                let hand = self.arena.get(child).hand();
+                self.proven(child, &board.put(hand).unwrap())
+            }))
+        }
     }
 
-    fn run(&mut self) -> McResult {
-        self.n_trial += 1;
-        let mut board = self.current_board.clone();
-
-        while !board.is_full() {
-            if board.win_index().is_some() {
-                // 次打つプレイヤーが勝利する
-                if board.next_player == self.current_board.next_player {
-                    self.n_win += 1;
-                    return McResult::Win;
-                } else {
-                    self.n_lose += 1;
-                    return McResult::Lose;
-                }
-            }
-
-            // 王手がかかっていたら、解除する
-            if let Some(index) = board.check_index() {
-                board = board.put(index).unwrap();
-                continue;
-            }
-
-            loop {
-                if let Some(b) = {
-                    let index = random::<usize>() & (N * N - 1);
-                    board.put(index)
-                } {
-                    board = b;
-                    break;
-                }
-            }
+    /**
+     * 試行回数が最大の子をたどる
+     */
+    fn pv(&self, id: NodeId) -> Vec<usize> {
+        let mut pv = vec![self.arena.get(id).hand()];
+        if let Some(child) = self
+            .arena
+            .children(id)
+            .max_by_key(|&child| self.arena.get(child).n_trial)
+        {
+            pv.extend(self.pv(child));
         }
+        pv
+    }
 
-        return McResult::Draw;
+    /**
+     * 親の手番側から見た統計
+     */
+    fn stats(&self) -> MoveStats {
+        let node = self.node();
+        MoveStats {
+            hand: node.hand(),
+            visits: node.n_trial,
+            win: node.n_lose,
+            draw: node.n_draw(),
+            lose: node.n_win,
+            prior: None,
+            pv: self.pv(Arena::ROOT),
+            proven: self
+                .proven(Arena::ROOT, &self.current_board)
+                .map(Proof::flip),
+        }
     }
 
-    pub fn expand(&mut self) -> (usize, usize, usize) {
-        if self.current_board.win_index().is_some() {
-            self.n_trial += 1;
-            self.n_win += 1;
+    fn export(&self, id: NodeId, options: &ExportOptions, depth: usize) -> export::Node {
+        let node = self.arena.get(id);
+        let mut exported =
+            export::Node::new(node.hand(), node.n_trial, node.n_lose, node.n_draw(), None);
+        if depth < options.depth {
+            exported.children = self
+                .arena
+                .children(id)
+                .filter(|&child| self.arena.get(child).n_trial >= options.min_visits)
+                .map(|child| self.export(child, options, depth + 1))
+                .collect();
+        }
+        exported
+    }
 
+    fn expand(
+        &mut self,
+        id: NodeId,
+        board: &BitBoard,
+        rng: &mut SearchRng,
+        stats: &mut SearchStats,
+    ) -> (usize, usize, usize) {
+        if board.win_index().is_some() {
+            self.arena.get_mut(id).add((1, 1, 0));
             return (1, 1, 0);
         }
+
+        // 上限に達していたら展開せずにプレイアウトだけする
+        if !self.arena.expand(id) {
+            let result = playout(board, rng, stats).counts();
+            self.arena.get_mut(id).add(result);
+            return result;
+        }
+        // UCB1は訪れた葉をすぐ展開する
+        stats.threshold_expansions += 1;
+
         let mut n_trial = 0;
         let mut n_win = 0;
         let mut n_lose = 0;
-        let mut leaves: [Option<Box<McTreeLeaf>>; N * N] = Default::default();
 
         for i in 0..N {
             for j in 0..N {
                 let index = i * N + j;
-                if let Some(board) = self.current_board.put(index) {
+                if let Some(board) = board.put(index) {
                     n_trial += 1;
-                    let mut leaf = Box::new(McTreeLeaf::new(board));
-                    let result = leaf.run();
+                    let mut leaf = arena::Node::new(index, 0);
+                    let result = playout(&board, rng, stats);
+                    leaf.add(result.counts());
                     if result == McResult::Win {
                         n_lose += 1;
                     } else {
                         n_win += 1;
                     }
-                    leaves[index] = Some(leaf);
+                    self.arena.push_child(id, leaf);
+                    stats.nodes += 1;
                 }
             }
         }
 
-        self.leaves = Some(leaves);
-        self.n_trial += n_trial;
-        self.n_win += n_win;
-        self.n_lose += n_lose;
+        self.arena.get_mut(id).add((n_trial, n_win, n_lose));
 
         (n_trial, n_win, n_lose)
     }
 
-    pub fn select(&mut self) -> (usize, usize, usize) {
-        if let Some(leaves) = &mut self.leaves {
+    fn select(
+        &mut self,
+        id: NodeId,
+        board: &BitBoard,
+        rng: &mut SearchRng,
+        stats: &mut SearchStats,
+        depth: usize,
+    ) -> (usize, usize, usize) {
+        let node = self.arena.get(id);
+        if node.is_expanded() {
             // choose appropriate k
-            let k = leaves
-                .into_iter()
-                .filter(|o| o.is_some())
-                .map(|o| (o.as_ref().map(|p| p.select_rate(self.n_trial)).unwrap(), o))
+            let k = self
+                .arena
+                .children(id)
+                .map(|child| (select_rate(self.arena.get(child), node.n_trial), child))
                 .max_by(|(r1, _), (r2, _)| r1.partial_cmp(r2).unwrap_or(std::cmp::Ordering::Equal));
-            if let Some((_, o)) = k {
-                let (n_trial, n_win, n_lose) = o.as_mut().unwrap().select();
+            if let Some((_, child)) = k {
+                let hand = self.arena.get(child).hand();
+                let (n_trial, n_win, n_lose) =
+                    self.select(child, &board.put(hand).unwrap(), rng, stats, depth + 1);
                 // flip win/lose and add
-                self.n_trial += n_trial;
-                self.n_win += n_lose;
-                self.n_lose += n_win;
+                self.arena.get_mut(id).add((n_trial, n_lose, n_win));
                 (n_trial, n_lose, n_win)
             } else {
                 // draw
-                self.n_trial += 1;
+                stats.reach(depth);
+                self.arena.get_mut(id).add((1, 0, 0));
                 (1, 0, 0)
             }
         } else {
-            self.expand()
+            stats.reach(depth);
+            let start = Instant::now();
+            let playout_time = stats.playout_time;
+            let result = self.expand(id, board, rng, stats);
+            stats.expand_time += start
+                .elapsed()
+                .saturating_sub(stats.playout_time - playout_time);
+            result
         }
     }
+
+    /**
+     * 根から一度たどる。上限に近ければ先に刈り込む。
+     */
+    fn search_once(&mut self, rng: &mut SearchRng, stats: &mut SearchStats) {
+        if self.arena.should_prune() {
+            let start = Instant::now();
+            self.arena.prune();
+            stats.prunes += 1;
+            stats.prune_time += start.elapsed();
+        }
+        let start = Instant::now();
+        let before = *stats;
+        let board = self.current_board.clone();
+        self.select(Arena::ROOT, &board, rng, stats, 1);
+        let other =
+            (stats.expand_time - before.expand_time) + (stats.playout_time - before.playout_time);
+        stats.select_time += start.elapsed().saturating_sub(other);
+    }
+}
+
+fn select_rate(node: &arena::Node, n_try: usize) -> f32 {
+    let c = 1.4f32;
+    (1f32 - node.win_rate()) + c * ((n_try as f32).ln() / node.n_trial as f32).sqrt()
+}
+
+/**
+ * 回数と時間を数えながらプレイアウトする
+ */
+fn playout(board: &BitBoard, rng: &mut SearchRng, stats: &mut SearchStats) -> McResult {
+    let start = Instant::now();
+    let result = run(board, rng);
+    stats.playouts += 1;
+    stats.playout_time += start.elapsed();
+    result
+}
+
+/**
+ * boardの手番側から見たプレイアウトの結果
+ */
+fn run(start: &BitBoard, rng: &mut SearchRng) -> McResult {
+    let mut board = start.clone();
+
+    while !board.is_full() {
+        if board.win_index().is_some() {
+            // 次打つプレイヤーが勝利する
+            if board.next_player == start.next_player {
+                return McResult::Win;
+            } else {
+                return McResult::Lose;
+            }
+        }
+
+        // 王手がかかっていたら、解除する
+        if let Some(index) = board.check_index() {
+            board = board.put(index).unwrap();
+            continue;
+        }
+
+        loop {
+            if let Some(b) = {
+                let index = rng.gen::<usize>() & (N * N - 1);
+                board.put(index)
+            } {
+                board = b;
+                break;
+            }
+        }
+    }
+
+    McResult::Draw
 }
 
 impl McTreeRoot {
     pub fn new(board: BitBoard) -> Self {
+        McTreeRoot::with_memory(board, MemoryLimit::default())
+    }
+
+    /**
+     * ノード数に上限のある木
+     */
+    pub fn with_memory(board: BitBoard, memory: MemoryLimit) -> Self {
         let leaves = (0..N * N)
-            .map(|index| board.put(index).map(McTreeLeaf::new))
+            .map(|index| board.put(index).map(|b| McTreeLeaf::new(b, index, &memory)))
             .collect::<Vec<_>>()
             .try_into()
             .unwrap();
         McTreeRoot {
             current_board: board,
-            leaves: leaves,
+            leaves,
+            memory,
+            seed: new_seed(),
+            rounds: 0,
+        }
+    }
+
+    pub fn board(&self) -> &BitBoard {
+        &self.current_board
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /**
+     * 乱数のseedを決め直す。同じseedで同じ回数探索すれば同じ結果になる。
+     */
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rounds = 0;
+    }
+
+    /**
+     * 使っているノードの数
+     */
+    pub fn nodes(&self) -> usize {
+        self.leaves
+            .iter()
+            .flatten()
+            .map(|leaf| leaf.arena.len())
+            .sum()
+    }
+
+    /**
+     * indexに置いた後の局面を根にした木を返す。調べ済みの部分木はそのまま引き継ぐ。
+     */
+    pub fn play(self, index: usize) -> Option<Self> {
+        let (memory, seed, rounds) = (self.memory, self.seed, self.rounds);
+        let leaf = self.leaves.into_iter().nth(index)??;
+        let board = leaf.current_board;
+        let mut leaves = McTreeRoot::with_memory(board.clone(), memory).leaves;
+        for child in leaf.arena.children(Arena::ROOT) {
+            let hand = leaf.arena.get(child).hand();
+            leaves[hand] = Some(McTreeLeaf {
+                current_board: board.put(hand).unwrap(),
+                arena: leaf.arena.subtree(child),
+            });
+        }
+        Some(McTreeRoot {
+            current_board: board,
+            leaves,
+            memory,
+            seed,
+            rounds,
+        })
+    }
+
+    /**
+     * 書き出し用に木をたどる
+     */
+    pub fn export(&self, options: &ExportOptions) -> export::Node {
+        if options.depth == 0 {
+            return export::Node::root(vec![]);
         }
+        export::Node::root(
+            self.leaves
+                .iter()
+                .flatten()
+                .filter(|leaf| leaf.node().n_trial >= options.min_visits)
+                .map(|leaf| leaf.export(Arena::ROOT, options, 1))
+                .collect(),
+        )
     }
 
     /**
-     * return (hand, eval)
+     * return (hand, eval)。evalは相手から見た勝率。
+     * 勝てる手は0、王手の応手は-100で、どちらも探索しない。
+     * n_totalが0なら探索せず、これまでの試行から選ぶ。
      */
     pub fn select(&mut self, n_total: usize) -> Option<(usize, f32)> {
+        if let Some(index) = self.current_board.win_index() {
+            return Some((index, 0f32));
+        }
         if let Some(index) = self.current_board.check_index() {
             return Some((index, -100f32));
         }
+        if n_total == 0 {
+            return self.result(None).select();
+        }
+        self.search(&SearchLimits::playouts(n_total)).select()
+    }
 
-        (0..N * N)
-            .par_bridge()
-            .filter_map(|index| {
-                let mut leaf = (self.leaves[index]).clone();
-                if leaf.is_none() {
-                    return None;
-                }
-                for _ in 0..n_total {
-                    leaf.as_mut().unwrap().select();
-                }
-                Some((leaf.unwrap().win_rate(), index))
-            })
-            .max_by(|(k1, _), (k2, _)| k2.partial_cmp(k1).unwrap_or(std::cmp::Ordering::Equal))
-            .map(|(rate, index)| (index, rate))
+    /**
+     * 今までの試行から候補手の統計を集める
+     */
+    fn result(&self, forced: Option<usize>) -> SearchResult {
+        let moves = self
+            .leaves
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| forced.is_none_or(|f| f == *index))
+            .filter_map(|(_, leaf)| Some(leaf.as_ref()?.stats()))
+            .collect();
+        SearchResult::new(moves, forced.is_some())
+    }
+
+    /**
+     * 全ての候補手を探索する。王手がかかっていれば応手だけを調べる。
+     */
+    pub fn search(&mut self, limits: &SearchLimits) -> SearchResult {
+        self.search_with_progress(limits, |_| ())
+    }
+
+    /**
+     * searchと同じ。まとめた試行を終えるたびに、それまでの統計でprogressを呼ぶ。
+     */
+    pub fn search_with_progress(
+        &mut self,
+        limits: &SearchLimits,
+        mut progress: impl FnMut(&SearchStats),
+    ) -> SearchResult {
+        // 勝てるなら勝つ
+        if let Some(index) = self.current_board.win_index() {
+            return SearchResult::new(vec![MoveStats::winning(index)], false);
+        }
+        let forced = self.current_board.check_index();
+
+        let start = Instant::now();
+        let mut stats = SearchStats::default();
+        let mut n = 0;
+        loop {
+            let round = limits.round(n);
+            // 候補手ごとに別の乱数列を使うので、スレッド数によらず同じ結果になる
+            let (seed, stream) = (self.seed, self.rounds * (N * N) as u64);
+            let round_stats = self
+                .leaves
+                .par_iter_mut()
+                .enumerate()
+                .filter(|(index, _)| forced.is_none_or(|f| f == *index))
+                .filter_map(|(index, leaf)| Some((index, leaf.as_mut()?)))
+                .map(|(index, leaf)| {
+                    let mut rng = rng_stream(seed, stream + index as u64);
+                    let mut stats = SearchStats::default();
+                    for _ in 0..round {
+                        leaf.search_once(&mut rng, &mut stats);
+                    }
+                    stats
+                })
+                .reduce(SearchStats::default, SearchStats::merge);
+            stats = stats.merge(round_stats);
+            self.rounds += 1;
+            n += round;
+            stats.elapsed = start.elapsed();
+            progress(&stats);
+            if limits.should_stop(n, start) {
+                break;
+            }
+        }
+
+        let mut result = self.result(forced);
+        stats.elapsed = start.elapsed();
+        result.stats = stats;
+        result
     }
 }
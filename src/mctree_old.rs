@@ -1,11 +1,16 @@
-use crate::{board::ArrayBoard, N};
-use rand::random;
+use crate::{
+    board::ArrayBoard,
+    search::{new_seed, rng_stream, SearchRng},
+    N,
+};
+use rand::Rng;
 use rayon::prelude::*;
 
 #[derive(Debug)]
 pub struct McTreeRoot {
     current_board: ArrayBoard,
     leaves: [Option<McTreeLeaf>; N * N],
+    seed: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -45,7 +50,7 @@ impl McTreeLeaf {
         (1f32 - self.win_rate()) + c * ((n_try as f32).ln() / self.n_trial as f32).sqrt()
     }
 
-    fn run(&mut self) -> McResult {
+    fn run(&mut self, rng: &mut SearchRng) -> McResult {
         self.n_trial += 1;
         let mut board = self.current_board.clone();
 
@@ -69,7 +74,7 @@ impl McTreeLeaf {
 
             loop {
                 if let Some(b) = {
-                    let index = random::<usize>() & (N * N - 1);
+                    let index = rng.gen::<usize>() & (N * N - 1);
                     board.put(index)
                 } {
                     board = b;
@@ -78,10 +83,10 @@ impl McTreeLeaf {
             }
         }
 
-        return McResult::Draw;
+        McResult::Draw
     }
 
-    pub fn expand(&mut self) -> (usize, usize, usize) {
+    pub fn expand(&mut self, rng: &mut SearchRng) -> (usize, usize, usize) {
         if self.current_board.win_index().is_some() {
             self.n_trial += 1;
             self.n_win += 1;
@@ -99,7 +104,7 @@ impl McTreeLeaf {
                 if let Some(board) = self.current_board.put(index) {
                     n_trial += 1;
                     let mut leaf = Box::new(McTreeLeaf::new(board));
-                    let result = leaf.run();
+                    let result = leaf.run(rng);
                     if result == McResult::Win {
                         n_lose += 1;
                     } else {
@@ -118,16 +123,16 @@ impl McTreeLeaf {
         (n_trial, n_win, n_lose)
     }
 
-    pub fn select(&mut self, n_try: usize) -> (usize, usize, usize) {
+    pub fn select(&mut self, n_try: usize, rng: &mut SearchRng) -> (usize, usize, usize) {
         if let Some(leaves) = &mut self.leaves {
             // choose appropriate k
             let k = leaves
-                .into_iter()
+                .iter_mut()
                 .filter(|o| o.is_some())
                 .map(|o| (o.as_ref().map(|p| p.select_rate(n_try)).unwrap(), o))
                 .max_by(|(r1, _), (r2, _)| r1.partial_cmp(r2).unwrap_or(std::cmp::Ordering::Equal));
             if let Some((_, o)) = k {
-                let (n_trial, n_win, n_lose) = o.as_mut().unwrap().select(n_try);
+                let (n_trial, n_win, n_lose) = o.as_mut().unwrap().select(n_try, rng);
                 // flip win/lose and add
                 self.n_trial += n_trial;
                 self.n_win += n_lose;
@@ -139,7 +144,7 @@ impl McTreeLeaf {
                 (1, 0, 0)
             }
         } else {
-            self.expand()
+            self.expand(rng)
         }
     }
 }
@@ -153,10 +158,22 @@ impl McTreeRoot {
             .unwrap();
         McTreeRoot {
             current_board: board,
-            leaves: leaves,
+            leaves,
+            seed: new_seed(),
         }
     }
 
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /**
+     * 乱数のseedを決め直す。同じseedからは同じ結果になる。
+     */
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
     /**
      * return (hand, eval)
      */
@@ -165,19 +182,35 @@ impl McTreeRoot {
             return Some((index, -100f32));
         }
 
+        // 候補手ごとに別の乱数列を使うので、スレッド数によらず同じ結果になる
+        let seed = self.seed;
         (0..N * N)
             .par_bridge()
             .filter_map(|index| {
-                let mut leaf = (self.leaves[index]).clone();
-                if leaf.is_none() {
-                    return None;
-                }
+                let mut leaf = self.leaves[index].clone()?;
+                let mut rng = rng_stream(seed, index as u64);
                 for n in 0..n_total {
-                    leaf.as_mut().unwrap().select(n + 1);
+                    leaf.select(n + 1, &mut rng);
                 }
-                Some((leaf.unwrap().win_rate(), index))
+                Some((leaf.win_rate(), index))
             })
             .max_by(|(k1, _), (k2, _)| k2.partial_cmp(k1).unwrap_or(std::cmp::Ordering::Equal))
             .map(|(rate, index)| (index, rate))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed() {
+        let board = ArrayBoard::new().put(0).unwrap();
+        let select = |seed| {
+            let mut tree = McTreeRoot::new(board.clone());
+            tree.set_seed(seed);
+            tree.select(50)
+        };
+        assert_eq!(select(1), select(1));
+    }
+}
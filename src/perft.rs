@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use crate::{bitboard::BitBoard, board::ArrayBoard, N};
+
+/**
+ * perftで数え上げる盤面
+ */
+pub trait PerftBoard: Clone {
+    fn play(&self, index_2d: usize) -> Option<Self>;
+    /**
+     * return true if the player who has just moved completed a line
+     */
+    fn is_won(&self) -> bool;
+    fn is_full(&self) -> bool;
+    /**
+     * key to identify the same position
+     */
+    fn key(&self) -> BitBoard;
+}
+
+impl PerftBoard for BitBoard {
+    fn play(&self, index_2d: usize) -> Option<Self> {
+        self.put(index_2d)
+    }
+
+    fn is_won(&self) -> bool {
+        BitBoard::is_won(self)
+    }
+
+    fn is_full(&self) -> bool {
+        BitBoard::is_full(self)
+    }
+
+    fn key(&self) -> BitBoard {
+        self.clone()
+    }
+}
+
+impl PerftBoard for ArrayBoard {
+    fn play(&self, index_2d: usize) -> Option<Self> {
+        self.put(index_2d)
+    }
+
+    fn is_won(&self) -> bool {
+        ArrayBoard::is_won(self)
+    }
+
+    fn is_full(&self) -> bool {
+        ArrayBoard::is_full(self)
+    }
+
+    fn key(&self) -> BitBoard {
+        self.into()
+    }
+}
+
+/**
+ * counts at a ply
+ */
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct PerftCount {
+    pub ply: usize,
+    /// number of move sequences reaching this ply
+    pub paths: u64,
+    /// number of distinct positions (classes of positions with symmetry reduction)
+    pub positions: u64,
+    /// move sequences ending with a completed line
+    pub win_paths: u64,
+    /// distinct positions with a completed line
+    pub win_positions: u64,
+    /// move sequences filling the board without a line
+    pub draw_paths: u64,
+}
+
+/**
+ * 1手目からdepth手目までの各手数について、手順数と局面数を数える。
+ * 勝負がついた局面からは先を展開しない。
+ * symmetryがtrueなら対称な局面を同一視する。
+ */
+pub fn perft<B: PerftBoard>(board: &B, depth: usize, symmetry: bool) -> Vec<PerftCount> {
+    let key = |board: &B| {
+        if symmetry {
+            board.key().canonical()
+        } else {
+            board.key()
+        }
+    };
+
+    let mut counts = vec![];
+    let mut frontier = HashMap::new();
+    frontier.insert(key(board), (board.clone(), 1u64));
+
+    for ply in 1..=depth {
+        let mut next: HashMap<BitBoard, (B, u64)> = HashMap::new();
+        let mut count = PerftCount {
+            ply,
+            ..Default::default()
+        };
+
+        for (board, paths) in frontier.into_values() {
+            if board.is_won() || board.is_full() {
+                continue;
+            }
+            for index_2d in 0..N * N {
+                if let Some(child) = board.play(index_2d) {
+                    count.paths += paths;
+                    if child.is_won() {
+                        count.win_paths += paths;
+                    } else if child.is_full() {
+                        count.draw_paths += paths;
+                    }
+                    next.entry(key(&child))
+                        .and_modify(|(_, n)| *n += paths)
+                        .or_insert((child, paths));
+                }
+            }
+        }
+
+        count.positions = next.len() as u64;
+        count.win_positions = next.values().filter(|(b, _)| b.is_won()).count() as u64;
+        counts.push(count);
+        frontier = next;
+    }
+
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perft_start() {
+        let counts = perft(&BitBoard::new(), 3, false);
+        assert_eq!(counts[0].paths, 16);
+        assert_eq!(counts[0].positions, 16);
+        assert_eq!(counts[1].paths, 256);
+        assert_eq!(counts[1].positions, 256);
+        assert_eq!(counts[2].paths, 4096);
+
+        let counts = perft(&BitBoard::new(), 1, true);
+        // 角、辺、中央
+        assert_eq!(counts[0].positions, 3);
+    }
+
+    #[test]
+    fn test_perft_boards() {
+        let bitboard = perft(&BitBoard::new(), 4, false);
+        let arrayboard = perft(&ArrayBoard::new(), 4, false);
+        assert_eq!(bitboard, arrayboard);
+
+        let symmetric = perft(&BitBoard::new(), 4, true);
+        for (s, b) in symmetric.iter().zip(bitboard.iter()) {
+            assert_eq!(s.paths, b.paths);
+            assert!(s.positions * 8 >= b.positions);
+            assert!(s.positions < b.positions);
+        }
+    }
+
+    #[test]
+    fn test_perft_win() {
+        // black: (0,0),(0,1),(0,2) white: (3,0),(3,1),(3,2)
+        let mut board = BitBoard::new();
+        for index in [0, 12, 1, 13, 2, 14] {
+            board = board.put(index).unwrap();
+        }
+        let counts = perft(&board, 2, false);
+        assert_eq!(counts[0].win_paths, 1);
+        assert_eq!(counts[0].win_positions, 1);
+        // 勝った局面は展開しない
+        assert_eq!(counts[1].paths, 15 * 16);
+        // 白は(3,3)を塞がれない限り勝つ
+        assert_eq!(counts[1].win_paths, 14);
+
+        let arrayboard: ArrayBoard = (&board).into();
+        assert_eq!(perft(&arrayboard, 2, false), counts);
+    }
+}
@@ -0,0 +1,96 @@
+use std::thread::{self, JoinHandle};
+
+use crate::{
+    search::SearchLimits,
+    simple_puct::{McTreeRoot, Policy},
+};
+
+/**
+ * 相手の手番の間に裏で探索を続ける。
+ *
+ * stopで止めて木を受け取り、相手の手でplayすれば調べた部分木を引き継げる。
+ * 受け取らずに捨てれば(待ったなど)探索は止まる。
+ * 先読みの量は相手が考える時間で変わるので、同じseedでも同じ結果にはならない。
+ */
+pub struct Ponder<T: Policy> {
+    limits: SearchLimits,
+    state: Option<State<T>>,
+}
+
+enum State<T: Policy> {
+    Searching(JoinHandle<McTreeRoot<T>>),
+    Idle(Box<McTreeRoot<T>>),
+}
+
+impl<T: Policy + 'static> Ponder<T> {
+    /**
+     * 候補手あたりmax_playouts回まで先読みする。相手が考え込んでも木が育ちすぎない。
+     */
+    pub fn start(mut tree: McTreeRoot<T>, max_playouts: usize) -> Self {
+        let board = tree.board();
+        if board.is_won() || board.is_full() {
+            return Self::hold(tree);
+        }
+        let limits = SearchLimits::playouts(max_playouts);
+        let thread_limits = limits.clone();
+        let handle = thread::spawn(move || {
+            tree.search(&thread_limits);
+            tree
+        });
+        Ponder {
+            limits,
+            state: Some(State::Searching(handle)),
+        }
+    }
+
+    /**
+     * 探索はせず木を預かるだけ
+     */
+    pub fn hold(tree: McTreeRoot<T>) -> Self {
+        Ponder {
+            limits: SearchLimits::default(),
+            state: Some(State::Idle(Box::new(tree))),
+        }
+    }
+
+    /**
+     * 探索を止めて木を返す
+     */
+    pub fn stop(mut self) -> McTreeRoot<T> {
+        self.limits.stop();
+        match self.state.take().unwrap() {
+            State::Searching(handle) => handle.join().unwrap(),
+            State::Idle(tree) => *tree,
+        }
+    }
+}
+
+impl<T: Policy> Drop for Ponder<T> {
+    fn drop(&mut self) {
+        self.limits.stop();
+        if let Some(State::Searching(handle)) = self.state.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bitboard::BitBoard, simple_puct::SimplePolicy};
+
+    #[test]
+    fn test_ponder() {
+        let ponder = Ponder::start(McTreeRoot::<SimplePolicy>::new(BitBoard::new()), 1_000_000);
+        let tree = ponder.stop();
+        let mut tree = tree.play(5).unwrap();
+        assert_eq!(tree.board(), &BitBoard::new().put(5).unwrap());
+        assert!(tree.select(100).is_some());
+
+        let tree = Ponder::hold(tree).stop();
+        assert_eq!(tree.board(), &BitBoard::new().put(5).unwrap());
+
+        // 捨てても止まる
+        drop(Ponder::start(tree, 1_000_000));
+    }
+}
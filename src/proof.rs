@@ -0,0 +1,366 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use crate::{bitboard::BitBoard, board::Player, format_column, parse_column, N};
+
+/**
+ * 証明木の一手。攻め方の手なら、子は受け方のすべての手で、ラインを完成させた手なら子はない。
+ * 受け方の手なら、子は攻め方の応手ひとつ。
+ */
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ProofNode {
+    pub hand: usize,
+    pub children: Vec<ProofNode>,
+}
+
+/**
+ * winnerがrootから必ず勝つことを示す木。
+ * movesはrootの手番の手で、winnerの手番ならひとつ、そうでなければすべての合法手。
+ */
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ProofTree {
+    pub root: BitBoard,
+    pub winner: Player,
+    pub moves: Vec<ProofNode>,
+}
+
+/**
+ * 証明木が正しくない理由
+ */
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ProofErrorKind {
+    /// 始めの局面で勝負がついている
+    Decided,
+    /// 置けない手
+    IllegalMove(usize),
+    /// 攻め方の手がひとつではない
+    ReplyCount(usize),
+    /// 受け方の手が抜けている
+    MissingDefence(usize),
+    /// 受け方の手が二度ある
+    DuplicateDefence(usize),
+    /// 受け方がラインを完成させた
+    DefenderWon(usize),
+    /// 攻め方がラインを完成させないまま盤が埋まった
+    Draw,
+    /// ラインを完成させた後に手が続いている
+    AfterWin,
+}
+
+/**
+ * 証明木の誤りと、そこに至る手順
+ */
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ProofError {
+    pub moves: Vec<usize>,
+    pub kind: ProofErrorKind,
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ProofErrorKind::Decided => write!(f, "the position is already decided")?,
+            ProofErrorKind::IllegalMove(hand) => {
+                write!(f, "illegal move {}", format_column(*hand))?
+            }
+            ProofErrorKind::ReplyCount(count) => {
+                write!(f, "the winner has {} replies instead of one", count)?
+            }
+            ProofErrorKind::MissingDefence(hand) => {
+                write!(f, "defence {} is not answered", format_column(*hand))?
+            }
+            ProofErrorKind::DuplicateDefence(hand) => {
+                write!(f, "defence {} appears twice", format_column(*hand))?
+            }
+            ProofErrorKind::DefenderWon(hand) => {
+                write!(f, "defence {} completes a line", format_column(*hand))?
+            }
+            ProofErrorKind::Draw => write!(f, "the board fills up without a line")?,
+            ProofErrorKind::AfterWin => write!(f, "moves continue after a completed line")?,
+        }
+        if !self.moves.is_empty() {
+            let moves = self.moves.iter().map(|&h| format_column(h));
+            write!(f, " after {}", moves.collect::<Vec<_>>().join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ProofError {}
+
+/**
+ * 証明木を確かめる。探索のコードは使わず、BitBoard::putとhas_lineだけで打ち直す。
+ * return 完成させたラインの数(木の葉の数)
+ */
+pub fn verify(tree: &ProofTree) -> Result<usize, ProofError> {
+    if tree.root.has_line(Player::Black) || tree.root.has_line(Player::White) {
+        return Err(ProofError {
+            moves: vec![],
+            kind: ProofErrorKind::Decided,
+        });
+    }
+    verify_moves(&tree.root, tree.winner, &tree.moves, &mut vec![])
+}
+
+fn verify_moves(
+    board: &BitBoard,
+    winner: Player,
+    moves: &[ProofNode],
+    path: &mut Vec<usize>,
+) -> Result<usize, ProofError> {
+    let error = |path: &Vec<usize>, kind| {
+        Err(ProofError {
+            moves: path.clone(),
+            kind,
+        })
+    };
+    if board.next_player() == winner {
+        let [node] = moves else {
+            return error(path, ProofErrorKind::ReplyCount(moves.len()));
+        };
+        let Some(next) = board.put(node.hand) else {
+            return error(path, ProofErrorKind::IllegalMove(node.hand));
+        };
+        path.push(node.hand);
+        let result = if next.has_line(winner) {
+            if node.children.is_empty() {
+                Ok(1)
+            } else {
+                error(path, ProofErrorKind::AfterWin)
+            }
+        } else {
+            verify_moves(&next, winner, &node.children, path)
+        };
+        path.pop();
+        result
+    } else {
+        let legal = (0..N * N).filter(|&hand| board.put(hand).is_some());
+        if legal.clone().count() == 0 {
+            return error(path, ProofErrorKind::Draw);
+        }
+        for hand in legal {
+            match moves.iter().filter(|node| node.hand == hand).count() {
+                0 => return error(path, ProofErrorKind::MissingDefence(hand)),
+                1 => (),
+                _ => return error(path, ProofErrorKind::DuplicateDefence(hand)),
+            }
+        }
+        let mut lines = 0;
+        for node in moves {
+            let Some(next) = board.put(node.hand) else {
+                return error(path, ProofErrorKind::IllegalMove(node.hand));
+            };
+            if next.has_line(winner.next_player()) {
+                return error(path, ProofErrorKind::DefenderWon(node.hand));
+            }
+            path.push(node.hand);
+            let result = verify_moves(&next, winner, &node.children, path);
+            path.pop();
+            lines += result?;
+        }
+        Ok(lines)
+    }
+}
+
+impl ProofTree {
+    /**
+     * 手の数
+     */
+    pub fn len(&self) -> usize {
+        fn count(nodes: &[ProofNode]) -> usize {
+            nodes.iter().map(|node| 1 + count(&node.children)).sum()
+        }
+        count(&self.moves)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /**
+     * テキストにする。局面と勝つ側の後に、一手一行で、深さの分だけ字下げして並べる。
+     */
+    pub fn to_text(&self) -> String {
+        fn write(nodes: &[ProofNode], depth: usize, text: &mut String) {
+            for node in nodes {
+                text.push_str(&" ".repeat(depth));
+                text.push_str(&format_column(node.hand));
+                text.push('\n');
+                write(&node.children, depth + 1, text);
+            }
+        }
+        let mut text = format!(
+            "position {}\nwinner {:?}\n",
+            self.root.position_string(),
+            self.winner
+        );
+        write(&self.moves, 0, &mut text);
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        // 空行は飛ばす
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let mut header = |name: &str| {
+            lines
+                .next()
+                .and_then(|(_, line)| line.strip_prefix(name))
+                .and_then(|rest| rest.strip_prefix(' '))
+                .map(str::to_owned)
+                .ok_or_else(|| format!("missing {}", name))
+        };
+        let position = header("position")?;
+        let root = BitBoard::from_position_string(&position)
+            .ok_or_else(|| format!("invalid position: {}", position))?;
+        let winner = match header("winner")?.as_str() {
+            "Black" => Player::Black,
+            "White" => Player::White,
+            winner => return Err(format!("invalid winner: {}", winner)),
+        };
+
+        // stack[d]は深さdの手。子はstack[d]の最後の手のもの
+        let mut stack: Vec<Vec<ProofNode>> = vec![vec![]];
+        let close = |stack: &mut Vec<Vec<ProofNode>>| {
+            let children = stack.pop().unwrap();
+            stack.last_mut().unwrap().last_mut().unwrap().children = children;
+        };
+        for (n, line) in lines {
+            let hand = line.trim_start_matches(' ');
+            let depth = line.len() - hand.len();
+            let hand = parse_column(hand).ok_or_else(|| format!("line {}: invalid move", n + 1))?;
+            if depth > stack.len() || (depth == stack.len() && stack[depth - 1].is_empty()) {
+                return Err(format!("line {}: invalid indent", n + 1));
+            }
+            while stack.len() > depth + 1 {
+                close(&mut stack);
+            }
+            if depth == stack.len() {
+                stack.push(vec![]);
+            }
+            stack[depth].push(ProofNode {
+                hand,
+                children: vec![],
+            });
+        }
+        while stack.len() > 1 {
+            close(&mut stack);
+        }
+        Ok(ProofTree {
+            root,
+            winner,
+            moves: stack.pop().unwrap(),
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        ProofTree::from_text(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfpn::{endgame, Dfpn};
+
+    #[test]
+    fn test_verify() {
+        let mut checked = [0; 2];
+        for board in (0..30).map(|seed| endgame(seed, 10)) {
+            for (i, winner) in [board.next_player(), board.next_player().next_player()]
+                .into_iter()
+                .enumerate()
+            {
+                let Some(tree) = Dfpn::new(winner).proof_tree(&board) else {
+                    continue;
+                };
+                assert!(verify(&tree).unwrap() > 0);
+                let text = tree.to_text();
+                assert_eq!(ProofTree::from_text(&text), Ok(tree.clone()));
+                checked[i] += 1;
+
+                // 受け方の手をひとつ消すと通らない
+                let mut broken = tree.clone();
+                let mut nodes = &mut broken.moves;
+                if board.next_player() == winner {
+                    nodes = &mut nodes[0].children;
+                }
+                if nodes.len() > 1 {
+                    let removed = nodes.pop().unwrap();
+                    let error = verify(&broken).unwrap_err();
+                    assert_eq!(error.kind, ProofErrorKind::MissingDefence(removed.hand));
+                }
+            }
+        }
+        assert!(checked[0] > 0 && checked[1] > 0, "{:?}", checked);
+    }
+
+    #[test]
+    fn test_errors() {
+        // 黒がa1, b1, c1と並べていて、d1で勝つ
+        let mut board = BitBoard::new();
+        for hand in [0, 15, 1, 14, 2, 13] {
+            board = board.put(hand).unwrap();
+        }
+        let leaf = |hand| ProofNode {
+            hand,
+            children: vec![],
+        };
+        let tree = ProofTree {
+            root: board.clone(),
+            winner: Player::Black,
+            moves: vec![leaf(3)],
+        };
+        assert_eq!(verify(&tree), Ok(1));
+        assert_eq!(ProofTree::from_text(&tree.to_text()), Ok(tree.clone()));
+        // エディタが足した空行は読み飛ばす
+        let text = tree.to_text().replace("\n", "\n\n");
+        assert_eq!(ProofTree::from_text(&text), Ok(tree.clone()));
+
+        let check = |moves, kind| {
+            let tree = ProofTree {
+                moves,
+                ..tree.clone()
+            };
+            assert_eq!(verify(&tree).unwrap_err().kind, kind);
+        };
+        check(vec![leaf(3), leaf(4)], ProofErrorKind::ReplyCount(2));
+        // a1の上は置けるが勝てず、白の手がすべて必要になる
+        check(vec![leaf(0)], ProofErrorKind::MissingDefence(0));
+        check(
+            vec![ProofNode {
+                hand: 3,
+                children: vec![leaf(4)],
+            }],
+            ProofErrorKind::AfterWin,
+        );
+
+        let tree = ProofTree {
+            winner: Player::White,
+            ..tree
+        };
+        assert_eq!(
+            verify(&tree).unwrap_err().kind,
+            ProofErrorKind::MissingDefence(0)
+        );
+        let moves = [3].into_iter().chain((0..N * N).filter(|&h| h != 3));
+        let tree = ProofTree {
+            moves: moves.map(leaf).collect(),
+            ..tree
+        };
+        assert_eq!(
+            verify(&tree).unwrap_err().kind,
+            ProofErrorKind::DefenderWon(3)
+        );
+
+        assert!(ProofTree::from_text("position ///////////////\nwinner Black\n a1\n").is_err());
+        assert!(ProofTree::from_text("position ///////////////\nwinner Red\n").is_err());
+        assert!(ProofTree::from_text("winner Black\n").is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use crate::{bitboard::BitBoard, parse_index, search::SearchLimits};
+
+/**
+ * 標準入出力でエンジンを動かすための行単位のプロトコル。
+ *
+ * yonmoku                          -> id ..., option ..., yonmokuok
+ * isready                          -> readyok
+ * setoption name NAME value VALUE
+ * newgame
+ * position startpos [moves i,j ...]
+ * position string POSITION [moves i,j ...]
+ * go [playouts N] [movetime MS] [infinite] -> info ..., bestmove i,j|none
+ * stop
+ * show
+ * quit
+ */
+#[derive(Clone, Debug)]
+pub enum Command {
+    Hello,
+    IsReady,
+    SetOption { name: String, value: String },
+    NewGame,
+    Position(BitBoard),
+    Go(SearchLimits),
+    Stop,
+    Show,
+    Quit,
+}
+
+fn parse_position(tokens: &[&str]) -> Result<BitBoard, String> {
+    let (mut board, rest) = match tokens {
+        ["startpos", rest @ ..] => (BitBoard::new(), rest),
+        ["string", position, rest @ ..] => (
+            BitBoard::from_position_string(position)
+                .ok_or_else(|| format!("invalid position string: {}", position))?,
+            rest,
+        ),
+        _ => return Err("position needs startpos or string".to_owned()),
+    };
+
+    match rest {
+        [] => Ok(board),
+        ["moves", moves @ ..] => {
+            for m in moves {
+                let index = parse_index(m).ok_or_else(|| format!("invalid move: {}", m))?;
+                board = board
+                    .try_play(index)
+                    .map_err(|e| format!("illegal move {}: {}", m, e))?;
+            }
+            Ok(board)
+        }
+        _ => Err(format!("unexpected token: {}", rest[0])),
+    }
+}
+
+fn parse_go(tokens: &[&str]) -> Result<SearchLimits, String> {
+    let mut limits = SearchLimits::default();
+    let mut tokens = tokens.iter();
+    while let Some(&token) = tokens.next() {
+        let mut value = || -> Result<u64, String> {
+            tokens
+                .next()
+                .and_then(|v| v.parse::<u64>().ok())
+                .ok_or_else(|| format!("{} needs a number", token))
+        };
+        match token {
+            "playouts" => limits.playouts = Some(value()? as usize),
+            "movetime" => limits.time = Some(Duration::from_millis(value()?)),
+            "infinite" => (),
+            _ => return Err(format!("unknown go option: {}", token)),
+        }
+    }
+    Ok(limits)
+}
+
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let tokens = line.split_whitespace().collect::<Vec<_>>();
+    match tokens.as_slice() {
+        ["yonmoku"] => Ok(Command::Hello),
+        ["isready"] => Ok(Command::IsReady),
+        ["setoption", "name", name, "value", value] => Ok(Command::SetOption {
+            name: name.to_string(),
+            value: value.to_string(),
+        }),
+        ["newgame"] => Ok(Command::NewGame),
+        ["position", rest @ ..] => parse_position(rest).map(Command::Position),
+        ["go", rest @ ..] => parse_go(rest).map(Command::Go),
+        ["stop"] => Ok(Command::Stop),
+        ["show"] => Ok(Command::Show),
+        ["quit"] => Ok(Command::Quit),
+        [] => Err("empty command".to_owned()),
+        _ => Err(format!("unknown command: {}", line.trim())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_position() {
+        let board = match parse_command("position startpos moves 1,1 1,1 3,3") {
+            Ok(Command::Position(board)) => board,
+            c => panic!("{:?}", c),
+        };
+        let expected = BitBoard::new()
+            .put(5)
+            .unwrap()
+            .put(5)
+            .unwrap()
+            .put(15)
+            .unwrap();
+        assert_eq!(board, expected);
+
+        let command = format!("position string {} moves 0,0", expected.position_string());
+        match parse_command(&command) {
+            Ok(Command::Position(board)) => assert_eq!(board, expected.put(0).unwrap()),
+            c => panic!("{:?}", c),
+        }
+
+        assert!(parse_command("position startpos moves 4,0").is_err());
+        assert!(parse_command("position string ///").is_err());
+    }
+
+    #[test]
+    fn test_parse_go() {
+        match parse_command("go playouts 100 movetime 2000") {
+            Ok(Command::Go(limits)) => {
+                assert_eq!(limits.playouts, Some(100));
+                assert_eq!(limits.time, Some(Duration::from_secs(2)));
+            }
+            c => panic!("{:?}", c),
+        }
+        match parse_command("go infinite") {
+            Ok(Command::Go(limits)) => {
+                assert_eq!(limits.playouts, None);
+                assert_eq!(limits.time, None);
+            }
+            c => panic!("{:?}", c),
+        }
+        assert!(parse_command("go playouts").is_err());
+        assert!(parse_command("go depth 3").is_err());
+    }
+}
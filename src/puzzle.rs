@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+
+use crate::{bitboard::BitBoard, format_column, parse_column, N};
+
+/**
+ * 詰み問題。boardの手番側がちょうどk手で勝ち、一手目の勝ち方はひとつだけ。
+ * solutionは攻め方と受け方の手を交互に並べた2k-1手で、受け方は最も長く粘る手を選ぶ。
+ */
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Puzzle {
+    pub board: BitBoard,
+    pub k: usize,
+    pub solution: Vec<usize>,
+}
+
+impl Puzzle {
+    /**
+     * "位置文字列 k 解答の手…"
+     */
+    pub fn to_line(&self) -> String {
+        let mut line = format!("{} {}", self.board.position_string(), self.k);
+        for &hand in &self.solution {
+            line.push(' ');
+            line.push_str(&format_column(hand));
+        }
+        line
+    }
+
+    /**
+     * to_lineの形を読む。解答が打てて、最後の手でラインが完成するかも確かめる。
+     */
+    pub fn from_line(line: &str) -> Result<Self, String> {
+        let mut tokens = line.split_whitespace();
+        let position = tokens.next().ok_or("empty line")?;
+        let board = BitBoard::from_position_string(position)
+            .ok_or_else(|| format!("invalid position: {}", position))?;
+        let k = tokens
+            .next()
+            .and_then(|k| k.parse::<usize>().ok())
+            .filter(|&k| k > 0)
+            .ok_or("invalid move count")?;
+        let solution = tokens
+            .map(|token| parse_column(token).ok_or_else(|| format!("invalid move: {}", token)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if solution.len() != 2 * k - 1 {
+            return Err(format!(
+                "{} moves in the solution of a win in {}",
+                solution.len(),
+                k
+            ));
+        }
+        let mut after = board.clone();
+        for &hand in &solution {
+            if after.is_won() {
+                return Err("the solution continues after a win".to_owned());
+            }
+            after = after
+                .put(hand)
+                .ok_or_else(|| format!("illegal move in the solution: {}", format_column(hand)))?;
+        }
+        if !after.is_won() {
+            return Err("the solution does not complete a line".to_owned());
+        }
+        Ok(Puzzle { board, k, solution })
+    }
+}
+
+/**
+ * 問題のファイルを読む。空行と#で始まる行は飛ばす。
+ */
+pub fn parse_puzzles(text: &str) -> Result<Vec<Puzzle>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(n, line)| Puzzle::from_line(line).map_err(|e| format!("line {}: {}", n + 1, e)))
+        .collect()
+}
+
+/**
+ * 手数を限った勝ちの探索。結果は(代表の盤面, 手数)ごとに覚える。
+ */
+#[derive(Clone, Debug, Default)]
+pub struct MateSearch {
+    table: HashMap<(BitBoard, usize), bool>,
+}
+
+/**
+ * 調べる手。王手がかかっていれば塞ぐ手だけで、他の手はすぐ負ける。
+ */
+fn hands(board: &BitBoard) -> Vec<usize> {
+    match board.check_index() {
+        Some(index) => vec![index],
+        None => (0..N * N).filter(|&h| board.put(h).is_some()).collect(),
+    }
+}
+
+impl MateSearch {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /**
+     * 手番側がk手以内に勝てるか
+     */
+    pub fn win_in(&mut self, board: &BitBoard, k: usize) -> bool {
+        if k == 0 {
+            return false;
+        }
+        if board.win_index().is_some() {
+            return true;
+        }
+        if k == 1 {
+            return false;
+        }
+        let key = (board.canonical(), k);
+        if let Some(&win) = self.table.get(&key) {
+            return win;
+        }
+        let win = hands(board)
+            .into_iter()
+            .any(|h| self.loses_in(&board.put(h).unwrap(), k - 1));
+        self.table.insert(key, win);
+        win
+    }
+
+    /**
+     * 手番側がどう受けても、相手がk手以内に勝つか
+     */
+    fn loses_in(&mut self, board: &BitBoard, k: usize) -> bool {
+        if board.win_index().is_some() || board.is_full() {
+            return false;
+        }
+        hands(board)
+            .into_iter()
+            .all(|h| self.win_in(&board.put(h).unwrap(), k))
+    }
+
+    /**
+     * k手以内に勝てる一手目
+     */
+    fn winning_moves(&mut self, board: &BitBoard, k: usize) -> Vec<usize> {
+        (0..N * N)
+            .filter(|&h| self.keeps_win(board, h, k))
+            .collect()
+    }
+
+    /**
+     * handを打っても、k手以内に勝てるか
+     */
+    pub fn keeps_win(&mut self, board: &BitBoard, hand: usize, k: usize) -> bool {
+        match board.put(hand) {
+            Some(next) => next.is_won() || (k > 1 && self.loses_in(&next, k - 1)),
+            None => false,
+        }
+    }
+
+    /**
+     * 相手がk手以内に勝つ局面で、最も長く粘る受け。k - 1手では負けない手があればそれ
+     */
+    pub fn defence(&mut self, board: &BitBoard, k: usize) -> Option<usize> {
+        let replies = hands(board);
+        let first = *replies.first()?;
+        let hand = replies
+            .into_iter()
+            .find(|&r| !self.win_in(&board.put(r).unwrap(), k - 1));
+        Some(hand.unwrap_or(first))
+    }
+
+    /**
+     * handではk手以内に勝てないとき、それを示す受け。相手が勝てるならその手。
+     * 盤が埋まって引き分けならNone
+     */
+    pub fn refutation(&mut self, board: &BitBoard, hand: usize, k: usize) -> Option<usize> {
+        let next = board.put(hand).filter(|next| !next.is_won())?;
+        if let Some(index) = next.win_index() {
+            return Some(index);
+        }
+        hands(&next)
+            .into_iter()
+            .find(|&r| !self.win_in(&next.put(r).unwrap(), k - 1))
+    }
+
+    /**
+     * 最短で勝つまでの手数。max_k手以内に勝てなければNone
+     */
+    pub fn mate_length(&mut self, board: &BitBoard, max_k: usize) -> Option<usize> {
+        (1..=max_k).find(|&k| self.win_in(board, k))
+    }
+
+    /**
+     * boardがちょうどk手で勝つ、一手目がひとつだけの局面なら問題にする
+     */
+    pub fn puzzle(&mut self, board: &BitBoard, k: usize) -> Option<Puzzle> {
+        if k == 0 || board.is_won() || !self.win_in(board, k) || self.win_in(board, k - 1) {
+            return None;
+        }
+        if self.winning_moves(board, k).len() != 1 {
+            return None;
+        }
+
+        let mut solution = vec![];
+        let mut after = board.clone();
+        for k in (1..=k).rev() {
+            let hand = self.winning_moves(&after, k)[0];
+            solution.push(hand);
+            after = after.put(hand).unwrap();
+            if after.is_won() {
+                break;
+            }
+            let reply = self.defence(&after, k - 1)?;
+            solution.push(reply);
+            after = after.put(reply).unwrap();
+        }
+        Some(Puzzle {
+            board: board.clone(),
+            k,
+            solution,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfpn::{endgame, Dfpn};
+
+    #[test]
+    fn test_win_in() {
+        let mut search = MateSearch::new();
+        let mut found = [0; 4];
+        for seed in 0..200 {
+            // 10から39手打った局面
+            let board = endgame(seed, N * N * N - 10 - seed as usize % 30);
+            assert_eq!(search.win_in(&board, 1), board.win_index().is_some());
+            let Some(k) = search.mate_length(&board, 3) else {
+                continue;
+            };
+            let mut dfpn = Dfpn::new(board.next_player());
+            assert_eq!(dfpn.search(&board, u64::MAX), Some(true));
+
+            if let Some(puzzle) = search.puzzle(&board, k) {
+                assert_eq!(puzzle.solution.len(), 2 * k - 1);
+                let line = puzzle.to_line();
+                for hand in (0..N * N).filter(|&h| board.put(h).is_some()) {
+                    let keeps = search.keeps_win(&board, hand, k);
+                    assert_eq!(keeps, hand == puzzle.solution[0]);
+                    let full = board.put(hand).unwrap().is_full();
+                    assert_eq!(
+                        search.refutation(&board, hand, k).is_some(),
+                        !keeps && !full
+                    );
+                }
+                assert_eq!(Puzzle::from_line(&line), Ok(puzzle));
+                found[k] += 1;
+            }
+        }
+        assert!(found[1] > 0 && found[2] > 0, "{:?}", found);
+    }
+
+    #[test]
+    fn test_parse() {
+        let text = "# puzzles\n\n/////bw//////////b 1 b2\n";
+        assert!(parse_puzzles(text).is_err());
+        let mut board = BitBoard::new();
+        for hand in [0, 15, 1, 14, 2, 13] {
+            board = board.put(hand).unwrap();
+        }
+        let line = format!("{} 1 d1", board.position_string());
+        let puzzles = parse_puzzles(&format!("# puzzles\n\n{}\n", line)).unwrap();
+        assert_eq!(puzzles.len(), 1);
+        assert_eq!(puzzles[0].solution, vec![3]);
+        assert!(Puzzle::from_line(&format!("{} 2 d1", board.position_string())).is_err());
+        assert!(Puzzle::from_line(&format!("{} 1 d2", board.position_string())).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    env, fmt,
+    io::{self, IsTerminal},
+};
+
+use crate::{
+    bitboard::BitBoard,
+    board::{ArrayBoard, Piece, Player},
+    N,
+};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const HIGHLIGHT: &str = "\x1b[43m";
+
+/**
+ * 盤面の表示。
+ *
+ * 各層を下から順に横に並べ、行がi、列がjになる。`b3`の記法に合わせて列を英字、行を1からの数字で示す。
+ * 色なしの場合、最後に置かれた石は大文字、強調するマスは[]で囲む。
+ */
+#[derive(Clone, Debug)]
+pub struct Render {
+    board: BitBoard,
+    color: bool,
+    compact: bool,
+    last: Option<usize>,
+    highlight: Vec<(usize, usize)>,
+}
+
+impl BitBoard {
+    pub fn render(&self) -> Render {
+        Render {
+            board: self.clone(),
+            color: false,
+            compact: false,
+            last: None,
+            highlight: vec![],
+        }
+    }
+}
+
+impl ArrayBoard {
+    pub fn render(&self) -> Render {
+        BitBoard::from(self).render()
+    }
+}
+
+impl fmt::Display for BitBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.render().fmt(f)
+    }
+}
+
+impl fmt::Display for ArrayBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.render().fmt(f)
+    }
+}
+
+fn player_color(player: Player) -> &'static str {
+    match player {
+        Player::Black => "\x1b[31m",
+        Player::White => "\x1b[34m",
+    }
+}
+
+impl Render {
+    /**
+     * ANSIエスケープで色をつける
+     */
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /**
+     * 標準出力が端末で、NO_COLORが設定されていなければ色をつける
+     */
+    pub fn auto_color(self) -> Self {
+        let color = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+        self.color(color)
+    }
+
+    /**
+     * ログ向けの一行表示
+     */
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    /**
+     * 最後に石を置いた列(index_2d)
+     */
+    pub fn last(mut self, last: Option<usize>) -> Self {
+        self.last = last;
+        self
+    }
+
+    /**
+     * 強調するマス(index_2d, k)
+     */
+    pub fn highlight(mut self, cells: impl IntoIterator<Item = (usize, usize)>) -> Self {
+        self.highlight.extend(cells);
+        self
+    }
+
+    /**
+     * 完成したラインを強調する
+     */
+    pub fn winning_line(self) -> Self {
+        let line = self.board.winning_line().unwrap_or_default();
+        self.highlight(line)
+    }
+
+    /**
+     * どちらかが置けばラインが完成するマスを強調する
+     */
+    pub fn threats(self) -> Self {
+        let black = self.board.threats(Player::Black);
+        let white = self.board.threats(Player::White);
+        self.highlight(black).highlight(white)
+    }
+
+    fn is_last(&self, index_2d: usize, k: usize) -> bool {
+        self.last == Some(index_2d) && self.board.height(index_2d) == k + 1
+    }
+
+    fn cell(&self, index_2d: usize, k: usize) -> String {
+        let piece = self.board.piece(index_2d, k);
+        let last = self.is_last(index_2d, k);
+        let highlight = self.highlight.contains(&(index_2d, k));
+
+        if self.color {
+            let (color, c) = match piece {
+                Piece::Black => (player_color(Player::Black), "●"),
+                Piece::White => (player_color(Player::White), "●"),
+                Piece::Empty => ("", "·"),
+            };
+            format!(
+                "{}{}{} {} {}",
+                if highlight { HIGHLIGHT } else { "" },
+                if last { BOLD } else { "" },
+                color,
+                if last { "◉" } else { c },
+                RESET
+            )
+        } else {
+            let c = match piece {
+                Piece::Black => 'b',
+                Piece::White => 'w',
+                Piece::Empty => '.',
+            };
+            let c = if last { c.to_ascii_uppercase() } else { c };
+            if highlight {
+                format!("[{}]", c)
+            } else {
+                format!(" {} ", c)
+            }
+        }
+    }
+
+    fn fmt_compact(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let layers = (0..N)
+            .map(|k| {
+                (0..N * N)
+                    .map(|index_2d| self.cell(index_2d, k).trim().to_owned())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        let next = match self.board.next_player() {
+            Player::Black => 'b',
+            Player::White => 'w',
+        };
+        write!(f, "{} {}", layers.join("|"), next)
+    }
+}
+
+impl fmt::Display for Render {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.compact {
+            return self.fmt_compact(f);
+        }
+
+        let width = 3 * N;
+        write!(f, "  ")?;
+        for k in 0..N {
+            write!(f, " {:<width$}", format!("layer {}", k + 1), width = width)?;
+        }
+        writeln!(f)?;
+        write!(f, "  ")?;
+        for _ in 0..N {
+            write!(f, " ")?;
+            for j in 0..N {
+                write!(f, " {} ", (b'a' + j as u8) as char)?;
+            }
+        }
+        writeln!(f)?;
+
+        for i in 0..N {
+            write!(f, "{} ", i + 1)?;
+            for k in 0..N {
+                write!(f, " ")?;
+                for j in 0..N {
+                    write!(f, "{}", self.cell(i * N + j, k))?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let board = BitBoard::new().put(5).unwrap().put(5).unwrap();
+        let expected = [
+            "   layer 1      layer 2      layer 3      layer 4     ",
+            "    a  b  c  d   a  b  c  d   a  b  c  d   a  b  c  d ",
+            "1   .  .  .  .   .  .  .  .   .  .  .  .   .  .  .  . ",
+            "2   .  b  .  .   .  W  .  .   .  .  .  .   .  .  .  . ",
+            "3   .  .  .  .   .  .  .  .   .  .  .  .   .  .  .  . ",
+            "4   .  .  .  .   .  .  .  .   .  .  .  .   .  .  .  . ",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(board.render().last(Some(5)).to_string(), expected);
+        assert_eq!(ArrayBoard::from(&board).to_string(), board.to_string());
+
+        assert_eq!(
+            board.render().compact(true).to_string(),
+            ".....b..........|.....w..........|................|................ b"
+        );
+    }
+
+    #[test]
+    fn test_render_highlight() {
+        let mut board = BitBoard::new();
+        for index in [0, 12, 1, 13, 2, 14, 3] {
+            board = board.put(index).unwrap();
+        }
+        let rendered = board.render().winning_line().to_string();
+        assert!(rendered.contains("1  [b][b][b][b]"));
+
+        let board = BitBoard::new().put(0).unwrap();
+        assert!(board
+            .render()
+            .color(true)
+            .to_string()
+            .contains(player_color(Player::Black)));
+    }
+}
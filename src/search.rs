@@ -0,0 +1,450 @@
+use rand::{rngs::StdRng, SeedableRng};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/**
+ * 一度にまとめて行う、候補手あたりの試行回数
+ */
+const ROUND: usize = 64;
+
+/**
+ * 探索の打ち切り条件。
+ * playoutsは各候補手あたりの試行回数。何も指定しなければstopされるまで探索する。
+ * 少なくとも1回は試行する。
+ */
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    pub playouts: Option<usize>,
+    pub time: Option<Duration>,
+    pub stop: Arc<AtomicBool>,
+}
+
+impl SearchLimits {
+    pub fn playouts(n_total: usize) -> Self {
+        SearchLimits {
+            playouts: Some(n_total),
+            ..Default::default()
+        }
+    }
+
+    pub fn time(time: Duration) -> Self {
+        SearchLimits {
+            time: Some(time),
+            ..Default::default()
+        }
+    }
+
+    /**
+     * stopされるまで終わらない探索か
+     */
+    pub fn is_infinite(&self) -> bool {
+        self.playouts.is_none() && self.time.is_none()
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /**
+     * n回試行した時点で探索を打ち切るか
+     */
+    pub fn should_stop(&self, n: usize, start: Instant) -> bool {
+        self.playouts.is_some_and(|playouts| n >= playouts)
+            || self.is_stopped()
+            || self.time.is_some_and(|time| start.elapsed() >= time)
+    }
+
+    /**
+     * n回試行した後、次にまとめて行う試行回数。時計と停止フラグはその間見ない。
+     */
+    pub fn round(&self, n: usize) -> usize {
+        match self.playouts {
+            Some(playouts) => ROUND.min(playouts.saturating_sub(n)).max(1),
+            None => ROUND,
+        }
+    }
+}
+
+/**
+ * 探索とプレイアウトで使う乱数。同じseedからは同じ列になる。
+ */
+pub type SearchRng = StdRng;
+
+/**
+ * 指定がないときのseed
+ */
+pub fn new_seed() -> u64 {
+    rand::random()
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/**
+ * seedからstream番目の別のseedを作る
+ */
+pub fn derive_seed(seed: u64, stream: u64) -> u64 {
+    splitmix64(seed ^ splitmix64(stream))
+}
+
+/**
+ * seedのstream番目の乱数列。並列に調べる候補手ごとに別の列を使う。
+ */
+pub fn rng_stream(seed: u64, stream: u64) -> SearchRng {
+    SearchRng::seed_from_u64(derive_seed(seed, stream))
+}
+
+/**
+ * 読み切った結果。手番側から見る。
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Proof {
+    Win,
+    Lose,
+    Draw,
+}
+
+impl Proof {
+    pub fn flip(self) -> Self {
+        match self {
+            Proof::Win => Proof::Lose,
+            Proof::Lose => Proof::Win,
+            Proof::Draw => Proof::Draw,
+        }
+    }
+
+    /**
+     * 子の局面(相手の手番)の結果から、この局面の結果を求める
+     */
+    pub fn from_children(children: impl IntoIterator<Item = Option<Proof>>) -> Option<Proof> {
+        let mut all_proven = true;
+        let mut draw = false;
+        let mut empty = true;
+        for child in children {
+            empty = false;
+            match child {
+                Some(Proof::Lose) => return Some(Proof::Win),
+                Some(Proof::Draw) => draw = true,
+                Some(Proof::Win) => (),
+                None => all_proven = false,
+            }
+        }
+        if empty || !all_proven {
+            None
+        } else if draw {
+            Some(Proof::Draw)
+        } else {
+            Some(Proof::Lose)
+        }
+    }
+}
+
+impl fmt::Display for Proof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Proof::Win => write!(f, "win"),
+            Proof::Lose => write!(f, "lose"),
+            Proof::Draw => write!(f, "draw"),
+        }
+    }
+}
+
+/**
+ * ルートの候補手ごとの統計。勝ち負けはルートの手番側から見る。
+ */
+#[derive(Clone, Debug)]
+pub struct MoveStats {
+    pub hand: usize,
+    pub visits: usize,
+    pub win: usize,
+    pub draw: usize,
+    pub lose: usize,
+    /// Policyによる事前評価
+    pub prior: Option<usize>,
+    /// 最善応手列。handから始まる。
+    pub pv: Vec<usize>,
+    pub proven: Option<Proof>,
+}
+
+impl MoveStats {
+    /**
+     * その手で勝ちが決まる
+     */
+    pub fn winning(hand: usize) -> Self {
+        MoveStats {
+            hand,
+            visits: 0,
+            win: 0,
+            draw: 0,
+            lose: 0,
+            prior: None,
+            pv: vec![hand],
+            proven: Some(Proof::Win),
+        }
+    }
+
+    pub fn win_rate(&self) -> f32 {
+        match self.proven {
+            Some(Proof::Win) => 1f32,
+            Some(Proof::Lose) => 0f32,
+            _ if self.visits == 0 => 0.5f32,
+            _ => (self.win as f32 + self.draw as f32 / 2.0) / self.visits as f32,
+        }
+    }
+
+    pub fn draw_rate(&self) -> f32 {
+        if self.visits == 0 {
+            0f32
+        } else {
+            self.draw as f32 / self.visits as f32
+        }
+    }
+
+    pub fn lose_rate(&self) -> f32 {
+        if self.visits == 0 {
+            0f32
+        } else {
+            self.lose as f32 / self.visits as f32
+        }
+    }
+
+    fn strength(&self) -> (u8, f32) {
+        let proven = match self.proven {
+            Some(Proof::Win) => 2,
+            Some(Proof::Lose) => 0,
+            _ => 1,
+        };
+        (proven, self.win_rate())
+    }
+}
+
+/**
+ * 探索中に数えた値。時間はスレッドごとの分を足し合わせるので、elapsedより長くなりうる。
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SearchStats {
+    pub playouts: usize,
+    /// 新しく作ったノードの数
+    pub nodes: usize,
+    /// 根の候補手から葉までたどった回数
+    pub descents: usize,
+    /// たどった深さ。根の候補手が1。
+    pub max_depth: usize,
+    pub total_depth: usize,
+    /// 試行回数がしきい値を超えて展開した回数
+    pub threshold_expansions: usize,
+    /// 王手がかかっていて、応手だけを展開した回数
+    pub forced_expansions: usize,
+    pub prunes: usize,
+    /// 子を選んでたどる時間
+    pub select_time: Duration,
+    /// 子を並べる時間。その中のプレイアウトは含まない。
+    pub expand_time: Duration,
+    pub playout_time: Duration,
+    pub prune_time: Duration,
+    /// 探索にかかった実時間
+    pub elapsed: Duration,
+}
+
+impl SearchStats {
+    pub fn average_depth(&self) -> f64 {
+        if self.descents == 0 {
+            0.0
+        } else {
+            self.total_depth as f64 / self.descents as f64
+        }
+    }
+
+    pub fn playouts_per_second(&self) -> f64 {
+        let elapsed = self.elapsed.as_secs_f64();
+        if elapsed == 0.0 {
+            0.0
+        } else {
+            self.playouts as f64 / elapsed
+        }
+    }
+
+    /**
+     * depthの葉に着いた
+     */
+    pub fn reach(&mut self, depth: usize) {
+        self.descents += 1;
+        self.max_depth = self.max_depth.max(depth);
+        self.total_depth += depth;
+    }
+
+    /**
+     * 別のスレッドや別の探索の分を足す
+     */
+    pub fn merge(mut self, other: SearchStats) -> Self {
+        self.playouts += other.playouts;
+        self.nodes += other.nodes;
+        self.descents += other.descents;
+        self.max_depth = self.max_depth.max(other.max_depth);
+        self.total_depth += other.total_depth;
+        self.threshold_expansions += other.threshold_expansions;
+        self.forced_expansions += other.forced_expansions;
+        self.prunes += other.prunes;
+        self.select_time += other.select_time;
+        self.expand_time += other.expand_time;
+        self.playout_time += other.playout_time;
+        self.prune_time += other.prune_time;
+        self.elapsed += other.elapsed;
+        self
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "playouts {} ({:.0}/s), nodes {}, depth max {} avg {:.2}, \
+             expansions threshold {} forced {}, prunes {}, \
+             time select {:.1}ms expand {:.1}ms playout {:.1}ms prune {:.1}ms, elapsed {:.1}ms",
+            self.playouts,
+            self.playouts_per_second(),
+            self.nodes,
+            self.max_depth,
+            self.average_depth(),
+            self.threshold_expansions,
+            self.forced_expansions,
+            self.prunes,
+            ms(self.select_time),
+            ms(self.expand_time),
+            ms(self.playout_time),
+            ms(self.prune_time),
+            ms(self.elapsed),
+        )
+    }
+}
+
+/**
+ * 探索結果。movesは強い順に並ぶ。
+ */
+#[derive(Clone, Debug, Default)]
+pub struct SearchResult {
+    pub moves: Vec<MoveStats>,
+    /// 王手の応手しか調べていない
+    pub forced: bool,
+    pub stats: SearchStats,
+}
+
+impl SearchResult {
+    pub fn new(mut moves: Vec<MoveStats>, forced: bool) -> Self {
+        moves.sort_by(|a, b| {
+            b.strength()
+                .partial_cmp(&a.strength())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        SearchResult {
+            moves,
+            forced,
+            stats: SearchStats::default(),
+        }
+    }
+
+    pub fn best(&self) -> Option<&MoveStats> {
+        self.moves.first()
+    }
+
+    /**
+     * return (hand, eval)。evalは相手から見た勝率。
+     */
+    pub fn select(&self) -> Option<(usize, f32)> {
+        self.best().map(|m| (m.hand, 1f32 - m.win_rate()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_should_stop() {
+        let start = Instant::now();
+        let limits = SearchLimits::playouts(10);
+        assert!(!limits.should_stop(9, start));
+        assert!(limits.should_stop(10, start));
+
+        assert_eq!(limits.round(0), 10);
+        assert_eq!(limits.round(10), 1);
+
+        let limits = SearchLimits::default();
+        assert_eq!(limits.round(0), ROUND);
+        assert!(!limits.should_stop(128, start));
+        limits.stop();
+        assert!(limits.should_stop(128, start));
+
+        let limits = SearchLimits::time(Duration::ZERO);
+        assert!(limits.should_stop(0, start));
+    }
+
+    #[test]
+    fn test_proof() {
+        assert_eq!(Proof::from_children([]), None);
+        assert_eq!(
+            Proof::from_children([None, Some(Proof::Lose)]),
+            Some(Proof::Win)
+        );
+        assert_eq!(Proof::from_children([None, Some(Proof::Win)]), None);
+        assert_eq!(
+            Proof::from_children([Some(Proof::Draw), Some(Proof::Win)]),
+            Some(Proof::Draw)
+        );
+        assert_eq!(
+            Proof::from_children([Some(Proof::Win), Some(Proof::Win)]),
+            Some(Proof::Lose)
+        );
+        assert_eq!(Proof::Lose.to_string(), "lose");
+    }
+
+    #[test]
+    fn test_result_order() {
+        let stats = |hand, win, proven| MoveStats {
+            hand,
+            visits: 10,
+            win,
+            draw: 0,
+            lose: 10 - win,
+            prior: None,
+            pv: vec![hand],
+            proven,
+        };
+        let result = SearchResult::new(
+            vec![
+                stats(0, 9, Some(Proof::Lose)),
+                stats(1, 3, None),
+                stats(2, 1, Some(Proof::Win)),
+                stats(3, 6, None),
+            ],
+            false,
+        );
+        let hands = result.moves.iter().map(|m| m.hand).collect::<Vec<_>>();
+        assert_eq!(hands, vec![2, 3, 1, 0]);
+        assert_eq!(result.select(), Some((2, 0f32)));
+    }
+
+    #[test]
+    fn test_rng_stream() {
+        let a = rng_stream(1, 0).gen::<u64>();
+        assert_eq!(rng_stream(1, 0).gen::<u64>(), a);
+        assert_ne!(rng_stream(1, 1).gen::<u64>(), a);
+        assert_ne!(rng_stream(2, 0).gen::<u64>(), a);
+    }
+}
@@ -1,37 +1,73 @@
-use std::marker::PhantomData;
+use std::{marker::PhantomData, time::Instant};
 
-use crate::{bitboard::BitBoard, N};
-use rand::random;
+use crate::{
+    arena::{self, Arena, MemoryLimit, NodeId},
+    bitboard::BitBoard,
+    export::{self, ExportOptions},
+    search::{
+        new_seed, rng_stream, MoveStats, Proof, SearchLimits, SearchResult, SearchRng, SearchStats,
+    },
+    N,
+};
+use rand::Rng;
 use rayon::prelude::*;
 
 const N_TRIAL_THRESHOLD: usize = 20;
 
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 pub struct McTreeRoot<T: Policy> {
     current_board: BitBoard,
     leaves: [Option<McTreeLeaf<T>>; N * N],
+    memory: MemoryLimit,
+    seed: u64,
+    /// これまでに探索した回数。乱数列を分けるのに使う。
+    rounds: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
     policy_type: PhantomData<fn() -> T>,
 }
 
+/**
+ * 根の候補手ひとつ分の部分木
+ */
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 struct McTreeLeaf<T: Policy> {
     current_board: BitBoard,
-    n_trial: usize,
-    n_win: usize,
-    n_lose: usize,
-    policy: usize,
-    leaves: Option<Vec<McTreeLeaf<T>>>,
-    is_checked: bool,
+    arena: Arena,
+    #[cfg_attr(feature = "serde", serde(skip))]
     policy_type: PhantomData<fn() -> T>,
 }
 
 #[derive(PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum McResult {
     Win,
     Lose,
     Draw,
 }
 
+impl McResult {
+    /**
+     * return (try, win, lose)
+     */
+    fn counts(&self) -> (usize, usize, usize) {
+        match self {
+            McResult::Win => (1, 1, 0),
+            McResult::Lose => (1, 0, 1),
+            McResult::Draw => (1, 0, 0),
+        }
+    }
+}
+
 pub trait Policy {
     fn put_with_policy(board: &BitBoard, index_2d: usize) -> Option<(BitBoard, usize)>;
 }
@@ -51,119 +87,144 @@ impl Policy for CountPolicy {
     }
 }
 
-impl<T: Policy> Clone for McTreeLeaf<T> {
-    fn clone(&self) -> Self {
-        Self {
-            current_board: self.current_board.clone(),
-            n_trial: self.n_trial.clone(),
-            n_win: self.n_win.clone(),
-            n_lose: self.n_lose.clone(),
-            policy: self.policy.clone(),
-            leaves: self.leaves.clone(),
-            is_checked: self.is_checked.clone(),
-            policy_type: PhantomData,
-        }
-    }
-}
-
 impl<T: Policy> McTreeLeaf<T> {
-    pub fn new(board: BitBoard, policy: usize) -> Self {
+    fn new(board: BitBoard, hand: usize, policy: usize, memory: &MemoryLimit) -> Self {
         McTreeLeaf {
-            is_checked: board.check_index().is_some(),
             current_board: board,
-            n_trial: 0,
-            n_win: 0,
-            n_lose: 0,
-            policy: policy,
-            leaves: None,
+            arena: Arena::new(arena::Node::new(hand, policy), memory),
             policy_type: PhantomData,
         }
     }
 
-    fn win_rate(&self) -> f32 {
-        (self.n_win as f32 + ((self.n_trial - self.n_win - self.n_lose) as f32) / 2.0)
-            / (self.n_trial as f32)
+    fn node(&self) -> &arena::Node {
+        self.arena.root()
     }
 
-    pub fn select_rate(&self, n_try: usize) -> f32 {
-        let c = 0.2f32;
-        (1f32 - self.win_rate())
-            + c * (self.policy as f32) * ((n_try as f32).sqrt() / self.n_trial as f32)
+    /**
+     * 手番側から見た読み切りの結果
+     */
+    fn proven(&self, id: NodeId, board: &BitBoard) -> Option<Proof> {
+        if board.win_index().is_some() {
+            Some(Proof::Win)
+        } else if board.is_full() {
+            Some(Proof::Draw)
+        } else if !self.arena.get(id).is_expanded() {
+            None
+        } else {
+            Proof::from_children(self.arena.children(id).map(|child| {
+                let hand = self.arena.get(child).hand();
+                self.proven(child, &board.put(hand).unwrap())
+            }))
+        }
     }
 
-    fn run(&mut self) -> McResult {
-        self.n_trial += 1;
-        let mut board = self.current_board.clone();
-
-        while !board.is_full() {
-            if board.win_index().is_some() {
-                // 次打つプレイヤーが勝利する
-                if board.next_player == self.current_board.next_player {
-                    self.n_win += 1;
-                    return McResult::Win;
-                } else {
-                    self.n_lose += 1;
-                    return McResult::Lose;
-                }
-            }
-
-            // 王手がかかっていたら、解除する
-            if let Some(index) = board.check_index() {
-                board = board.put(index).unwrap();
-                continue;
-            }
+    /**
+     * 試行回数が最大の子をたどる
+     */
+    fn pv(&self, id: NodeId) -> Vec<usize> {
+        let mut pv = vec![self.arena.get(id).hand()];
+        if let Some(child) = self
+            .arena
+            .children(id)
+            .max_by_key(|&child| self.arena.get(child).n_trial)
+        {
+            pv.extend(self.pv(child));
+        }
+        pv
+    }
 
-            loop {
-                if let Some(b) = {
-                    let index = random::<usize>() & (N * N - 1);
-                    board.put(index)
-                } {
-                    board = b;
-                    break;
-                }
-            }
+    /**
+     * 親の手番側から見た統計
+     */
+    fn stats(&self) -> MoveStats {
+        let node = self.node();
+        MoveStats {
+            hand: node.hand(),
+            visits: node.n_trial,
+            win: node.n_lose,
+            draw: node.n_draw(),
+            lose: node.n_win,
+            prior: Some(node.policy()),
+            pv: self.pv(Arena::ROOT),
+            proven: self
+                .proven(Arena::ROOT, &self.current_board)
+                .map(Proof::flip),
         }
+    }
 
-        return McResult::Draw;
+    fn export(&self, id: NodeId, options: &ExportOptions, depth: usize) -> export::Node {
+        let node = self.arena.get(id);
+        let mut exported = export::Node::new(
+            node.hand(),
+            node.n_trial,
+            node.n_lose,
+            node.n_draw(),
+            Some(node.policy()),
+        );
+        if depth < options.depth {
+            exported.children = self
+                .arena
+                .children(id)
+                .filter(|&child| self.arena.get(child).n_trial >= options.min_visits)
+                .map(|child| self.export(child, options, depth + 1))
+                .collect();
+            exported.children.sort_by_key(|child| child.hand);
+        }
+        exported
     }
 
-    fn run_and_push(&mut self, index: usize) -> Option<McResult> {
-        if let Some((board, policy)) = T::put_with_policy(&self.current_board, index) {
-            self.n_trial += 1;
-            let mut leaf = McTreeLeaf::new(board, policy);
-            let result = leaf.run();
-            if result == McResult::Win {
-                self.n_lose += 1;
-            } else {
-                self.n_win += 1;
-            }
-            self.leaves.as_mut().unwrap().push(leaf);
-            Some(result)
+    fn run_and_push(
+        &mut self,
+        id: NodeId,
+        board: &BitBoard,
+        index: usize,
+        rng: &mut SearchRng,
+        stats: &mut SearchStats,
+    ) -> Option<McResult> {
+        let (board, policy) = T::put_with_policy(board, index)?;
+        let mut leaf = arena::Node::new(index, policy);
+        let result = playout(&board, rng, stats);
+        leaf.add(result.counts());
+        let node = self.arena.get_mut(id);
+        node.n_trial += 1;
+        if result == McResult::Win {
+            node.n_lose += 1;
         } else {
-            None
+            node.n_win += 1;
         }
+        self.arena.push_child(id, leaf);
+        stats.nodes += 1;
+        Some(result)
     }
 
     // return (try, win, lose)
-    pub fn expand(&mut self) -> (usize, usize, usize) {
-        if self.current_board.win_index().is_some() {
-            self.n_trial += 1;
-            self.n_win += 1;
-
+    fn expand(
+        &mut self,
+        id: NodeId,
+        board: &BitBoard,
+        rng: &mut SearchRng,
+        stats: &mut SearchStats,
+    ) -> (usize, usize, usize) {
+        if board.win_index().is_some() {
+            self.arena.get_mut(id).add((1, 1, 0));
             return (1, 1, 0);
         }
 
-        self.leaves = Some(vec![]);
+        // 上限に達していたら展開せずにプレイアウトだけする
+        if !self.arena.expand(id) {
+            let result = playout(board, rng, stats).counts();
+            self.arena.get_mut(id).add(result);
+            return result;
+        }
 
-        if self.is_checked {
-            let index = self.current_board.check_index().unwrap();
-            let result = self.run_and_push(index).unwrap();
-            return match result {
-                McResult::Win => (1, 1, 0),
-                McResult::Lose => (1, 0, 1),
-                McResult::Draw => (1, 0, 0),
-            };
+        if let Some(index) = board.check_index() {
+            stats.forced_expansions += 1;
+            return self
+                .run_and_push(id, board, index, rng, stats)
+                .unwrap()
+                .counts();
         }
+        stats.threshold_expansions += 1;
 
         let mut n_trial = 0;
         let mut n_win = 0;
@@ -172,7 +233,7 @@ impl<T: Policy> McTreeLeaf<T> {
         for i in 0..N {
             for j in 0..N {
                 let index = i * N + j;
-                if let Some(result) = self.run_and_push(index) {
+                if let Some(result) = self.run_and_push(id, board, index, rng, stats) {
                     n_trial += 1;
                     match result {
                         McResult::Win => n_win += 1,
@@ -187,73 +248,445 @@ impl<T: Policy> McTreeLeaf<T> {
     }
 
     // return (try, win, lose)
-    pub fn select(&mut self) -> (usize, usize, usize) {
-        if let Some(leaves) = &mut self.leaves {
+    fn select(
+        &mut self,
+        id: NodeId,
+        board: &BitBoard,
+        rng: &mut SearchRng,
+        stats: &mut SearchStats,
+        depth: usize,
+    ) -> (usize, usize, usize) {
+        let node = self.arena.get(id);
+        if node.is_expanded() {
             // choose appropriate k
-            let k = leaves
-                .into_iter()
-                .map(|o| (o.select_rate(self.n_trial), o))
+            let k = self
+                .arena
+                .children(id)
+                .map(|child| (select_rate(self.arena.get(child), node.n_trial), child))
                 .max_by(|(r1, _), (r2, _)| r1.partial_cmp(r2).unwrap_or(std::cmp::Ordering::Equal));
-            if let Some((_, o)) = k {
-                let (n_trial, n_win, n_lose) = o.select();
+            if let Some((_, child)) = k {
+                let hand = self.arena.get(child).hand();
+                let (n_trial, n_win, n_lose) =
+                    self.select(child, &board.put(hand).unwrap(), rng, stats, depth + 1);
                 // flip win/lose and add
-                self.n_trial += n_trial;
-                self.n_win += n_lose;
-                self.n_lose += n_win;
+                self.arena.get_mut(id).add((n_trial, n_lose, n_win));
                 (n_trial, n_lose, n_win)
             } else {
                 // draw
-                self.n_trial += 1;
+                stats.reach(depth);
+                self.arena.get_mut(id).add((1, 0, 0));
                 (1, 0, 0)
             }
-        } else if self.is_checked || self.n_trial > N_TRIAL_THRESHOLD {
+        } else if board.check_index().is_some() || node.n_trial > N_TRIAL_THRESHOLD {
             // 王手がかかっていたら試行回数は無視する。
-            self.expand()
+            stats.reach(depth);
+            let start = Instant::now();
+            let playout_time = stats.playout_time;
+            let result = self.expand(id, board, rng, stats);
+            stats.expand_time += start
+                .elapsed()
+                .saturating_sub(stats.playout_time - playout_time);
+            result
         } else {
-            match self.run() {
-                McResult::Win => (1, 1, 0),
-                McResult::Lose => (1, 0, 1),
-                McResult::Draw => (1, 0, 0),
+            stats.reach(depth);
+            let result = playout(board, rng, stats).counts();
+            self.arena.get_mut(id).add(result);
+            result
+        }
+    }
+
+    /**
+     * 根から一度たどる。上限に近ければ先に刈り込む。
+     */
+    fn search_once(&mut self, rng: &mut SearchRng, stats: &mut SearchStats) {
+        if self.arena.should_prune() {
+            let start = Instant::now();
+            self.arena.prune();
+            stats.prunes += 1;
+            stats.prune_time += start.elapsed();
+        }
+        let start = Instant::now();
+        let before = *stats;
+        let board = self.current_board.clone();
+        self.select(Arena::ROOT, &board, rng, stats, 1);
+        let other =
+            (stats.expand_time - before.expand_time) + (stats.playout_time - before.playout_time);
+        stats.select_time += start.elapsed().saturating_sub(other);
+    }
+}
+
+fn select_rate(node: &arena::Node, n_try: usize) -> f32 {
+    let c = 0.2f32;
+    (1f32 - node.win_rate())
+        + c * (node.policy() as f32) * ((n_try as f32).sqrt() / node.n_trial as f32)
+}
+
+/**
+ * 回数と時間を数えながらプレイアウトする
+ */
+fn playout(board: &BitBoard, rng: &mut SearchRng, stats: &mut SearchStats) -> McResult {
+    let start = Instant::now();
+    let result = run(board, rng);
+    stats.playouts += 1;
+    stats.playout_time += start.elapsed();
+    result
+}
+
+/**
+ * boardの手番側から見たプレイアウトの結果
+ */
+fn run(start: &BitBoard, rng: &mut SearchRng) -> McResult {
+    let mut board = start.clone();
+
+    while !board.is_full() {
+        if board.win_index().is_some() {
+            // 次打つプレイヤーが勝利する
+            if board.next_player == start.next_player {
+                return McResult::Win;
+            } else {
+                return McResult::Lose;
+            }
+        }
+
+        // 王手がかかっていたら、解除する
+        if let Some(index) = board.check_index() {
+            board = board.put(index).unwrap();
+            continue;
+        }
+
+        loop {
+            if let Some(b) = {
+                let index = rng.gen::<usize>() & (N * N - 1);
+                board.put(index)
+            } {
+                board = b;
+                break;
             }
         }
     }
+
+    McResult::Draw
 }
 
 impl<T: Policy> McTreeRoot<T> {
     pub fn new(board: BitBoard) -> Self {
+        McTreeRoot::with_memory(board, MemoryLimit::default())
+    }
+
+    /**
+     * ノード数に上限のある木
+     */
+    pub fn with_memory(board: BitBoard, memory: MemoryLimit) -> Self {
         let leaves = (0..N * N)
-            .map(|index| T::put_with_policy(&board, index).map(|(b, p)| McTreeLeaf::<T>::new(b, p)))
+            .map(|index| {
+                T::put_with_policy(&board, index)
+                    .map(|(b, p)| McTreeLeaf::<T>::new(b, index, p, &memory))
+            })
             .collect::<Vec<_>>()
             .try_into()
             .unwrap_or_else(|_| panic!("never reach here."));
         McTreeRoot {
             current_board: board,
-            leaves: leaves,
+            leaves,
+            memory,
+            seed: new_seed(),
+            rounds: 0,
+            policy_type: PhantomData,
+        }
+    }
+
+    pub fn board(&self) -> &BitBoard {
+        &self.current_board
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /**
+     * 乱数のseedを決め直す。同じseedで同じ回数探索すれば同じ結果になる。
+     */
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rounds = 0;
+    }
+
+    /**
+     * 使っているノードの数
+     */
+    pub fn nodes(&self) -> usize {
+        self.leaves
+            .iter()
+            .flatten()
+            .map(|leaf| leaf.arena.len())
+            .sum()
+    }
+
+    /**
+     * indexに置いた後の局面を根にした木を返す。調べ済みの部分木はそのまま引き継ぐ。
+     */
+    pub fn play(self, index: usize) -> Option<Self> {
+        let (memory, seed, rounds) = (self.memory, self.seed, self.rounds);
+        let leaf = self.leaves.into_iter().nth(index)??;
+        let board = leaf.current_board;
+        let children = leaf.arena.children(Arena::ROOT);
+        let leaves = (0..N * N)
+            .map(|index| {
+                match children
+                    .clone()
+                    .find(|&child| leaf.arena.get(child).hand() == index)
+                {
+                    Some(child) => Some(McTreeLeaf {
+                        current_board: board.put(index).unwrap(),
+                        arena: leaf.arena.subtree(child),
+                        policy_type: PhantomData,
+                    }),
+                    None => T::put_with_policy(&board, index)
+                        .map(|(b, p)| McTreeLeaf::<T>::new(b, index, p, &memory)),
+                }
+            })
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap_or_else(|_| panic!("never reach here."));
+        Some(McTreeRoot {
+            current_board: board,
+            leaves,
+            memory,
+            seed,
+            rounds,
             policy_type: PhantomData,
+        })
+    }
+
+    /**
+     * 書き出し用に木をたどる
+     */
+    pub fn export(&self, options: &ExportOptions) -> export::Node {
+        if options.depth == 0 {
+            return export::Node::root(vec![]);
         }
+        export::Node::root(
+            self.leaves
+                .iter()
+                .flatten()
+                .filter(|leaf| leaf.node().n_trial >= options.min_visits)
+                .map(|leaf| leaf.export(Arena::ROOT, options, 1))
+                .collect(),
+        )
     }
 
     /**
-     * return (hand, eval)
+     * return (hand, eval)。evalは相手から見た勝率。
+     * 勝てる手は0、王手の応手は-100で、どちらも探索しない。
+     * n_totalが0なら探索せず、これまでの試行から選ぶ。
      */
     pub fn select(&mut self, n_total: usize) -> Option<(usize, f32)> {
+        if let Some(index) = self.current_board.win_index() {
+            return Some((index, 0f32));
+        }
         if let Some(index) = self.current_board.check_index() {
             return Some((index, -100f32));
         }
+        if n_total == 0 {
+            return self.result(None).select();
+        }
+        self.search(&SearchLimits::playouts(n_total)).select()
+    }
 
-        (0..N * N)
-            .par_bridge()
-            .filter_map(|index| {
-                let mut leaf = (self.leaves[index]).clone();
-                if leaf.is_none() {
-                    return None;
-                }
-                for _ in 0..n_total {
-                    leaf.as_mut().unwrap().select();
-                }
-                Some((leaf.unwrap().win_rate(), index))
-            })
-            .max_by(|(k1, _), (k2, _)| k2.partial_cmp(k1).unwrap_or(std::cmp::Ordering::Equal))
-            .map(|(rate, index)| (index, rate))
+    /**
+     * 今までの試行から候補手の統計を集める
+     */
+    fn result(&self, forced: Option<usize>) -> SearchResult {
+        let moves = self
+            .leaves
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| forced.is_none_or(|f| f == *index))
+            .filter_map(|(_, leaf)| Some(leaf.as_ref()?.stats()))
+            .collect();
+        SearchResult::new(moves, forced.is_some())
+    }
+
+    /**
+     * 全ての候補手を探索する。王手がかかっていれば応手だけを調べる。
+     */
+    pub fn search(&mut self, limits: &SearchLimits) -> SearchResult {
+        self.search_with_progress(limits, |_| ())
+    }
+
+    /**
+     * searchと同じ。まとめた試行を終えるたびに、それまでの統計でprogressを呼ぶ。
+     */
+    pub fn search_with_progress(
+        &mut self,
+        limits: &SearchLimits,
+        mut progress: impl FnMut(&SearchStats),
+    ) -> SearchResult {
+        // 勝てるなら勝つ
+        if let Some(index) = self.current_board.win_index() {
+            return SearchResult::new(vec![MoveStats::winning(index)], false);
+        }
+        let forced = self.current_board.check_index();
+
+        let start = Instant::now();
+        let mut stats = SearchStats::default();
+        let mut n = 0;
+        loop {
+            let round = limits.round(n);
+            // 候補手ごとに別の乱数列を使うので、スレッド数によらず同じ結果になる
+            let (seed, stream) = (self.seed, self.rounds * (N * N) as u64);
+            let round_stats = self
+                .leaves
+                .par_iter_mut()
+                .enumerate()
+                .filter(|(index, _)| forced.is_none_or(|f| f == *index))
+                .filter_map(|(index, leaf)| Some((index, leaf.as_mut()?)))
+                .map(|(index, leaf)| {
+                    let mut rng = rng_stream(seed, stream + index as u64);
+                    let mut stats = SearchStats::default();
+                    for _ in 0..round {
+                        leaf.search_once(&mut rng, &mut stats);
+                    }
+                    stats
+                })
+                .reduce(SearchStats::default, SearchStats::merge);
+            stats = stats.merge(round_stats);
+            self.rounds += 1;
+            n += round;
+            stats.elapsed = start.elapsed();
+            progress(&stats);
+            if limits.should_stop(n, start) {
+                break;
+            }
+        }
+
+        let mut result = self.result(forced);
+        stats.elapsed = start.elapsed();
+        result.stats = stats;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::WhenFull;
+
+    #[test]
+    fn test_search() {
+        // 黒: (0,0),(0,1),(0,2) 白: (3,0),(3,1)
+        let mut board = BitBoard::new();
+        for index in [0, 12, 1, 13, 2] {
+            board = board.put(index).unwrap();
+        }
+        // 白は(0,3)を受けるしかない
+        let result =
+            McTreeRoot::<SimplePolicy>::new(board.clone()).search(&SearchLimits::playouts(10));
+        assert!(result.forced);
+        assert_eq!(result.moves.len(), 1);
+        assert_eq!(result.best().unwrap().hand, 3);
+        let mut tree = McTreeRoot::<SimplePolicy>::new(board.clone());
+        assert_eq!(tree.select(0), Some((3, -100f32)));
+
+        // 白が受けなければ黒の勝ち
+        let board = board.put(14).unwrap();
+        let mut tree = McTreeRoot::<SimplePolicy>::new(board.clone());
+        assert_eq!(tree.select(100), Some((3, 0f32)));
+        assert_eq!(
+            tree.nodes(),
+            McTreeRoot::<SimplePolicy>::new(board.clone()).nodes()
+        );
+        let result = McTreeRoot::<SimplePolicy>::new(board).search(&SearchLimits::playouts(10));
+        let best = result.best().unwrap();
+        assert_eq!(best.hand, 3);
+        assert_eq!(best.proven, Some(Proof::Win));
+        assert_eq!(result.select(), Some((3, 0f32)));
+    }
+
+    #[test]
+    fn test_select_without_playouts() {
+        let mut tree = McTreeRoot::<SimplePolicy>::new(BitBoard::new());
+        let nodes = tree.nodes();
+        assert!(tree.select(0).is_some());
+        assert_eq!(tree.nodes(), nodes);
+        assert!(tree.select(100).is_some());
+        assert!(tree.nodes() > nodes);
+    }
+
+    #[test]
+    fn test_seed() {
+        let search = |seed| {
+            let mut tree = McTreeRoot::<SimplePolicy>::new(BitBoard::new());
+            tree.set_seed(seed);
+            let result = tree.search(&SearchLimits::playouts(200));
+            let mut tree = tree.play(result.best().unwrap().hand).unwrap();
+            let result = tree.search(&SearchLimits::playouts(200));
+            result
+                .moves
+                .iter()
+                .map(|m| (m.hand, m.visits, m.win, m.lose))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(search(7), search(7));
+        assert_ne!(search(7), search(8));
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut tree = McTreeRoot::<SimplePolicy>::new(BitBoard::new());
+        tree.set_seed(1);
+        let stats = tree.search(&SearchLimits::playouts(100)).stats;
+        // 候補手ごとに100回たどる
+        assert_eq!(stats.descents, N * N * 100);
+        assert!(stats.playouts >= stats.descents);
+        assert!(stats.threshold_expansions > 0);
+        assert_eq!(stats.nodes, tree.nodes() - N * N);
+        assert!(stats.max_depth >= 2);
+        assert!(stats.average_depth() >= 1.0);
+        assert!(stats.elapsed > std::time::Duration::ZERO);
+
+        // 王手がかかっていれば応手だけを展開する
+        let mut board = BitBoard::new();
+        for index in [0, 12, 1, 13, 2] {
+            board = board.put(index).unwrap();
+        }
+        let result = McTreeRoot::<SimplePolicy>::new(board).search(&SearchLimits::playouts(100));
+        assert!(result.forced);
+        assert_eq!(result.stats.descents, 100);
+    }
+
+    #[test]
+    fn test_memory() {
+        let board = BitBoard::new().put(5).unwrap();
+        for when_full in [WhenFull::Stop, WhenFull::Prune] {
+            let memory = MemoryLimit::nodes(2_000, when_full);
+            let mut tree = McTreeRoot::<SimplePolicy>::with_memory(board.clone(), memory);
+            let result = tree.search(&SearchLimits::playouts(500));
+            assert!(tree.nodes() <= 2_000);
+            assert_eq!(result.moves.len(), N * N);
+            assert!(result.moves.iter().all(|m| m.visits >= 500));
+
+            // 引き継いだ部分木も上限を守る
+            let mut tree = tree.play(result.best().unwrap().hand).unwrap();
+            tree.search(&SearchLimits::playouts(500));
+            assert!(tree.nodes() <= 2_000);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut tree = McTreeRoot::<SimplePolicy>::new(BitBoard::new().put(5).unwrap());
+        tree.search(&SearchLimits::playouts(100));
+        let json = serde_json::to_string(&tree).unwrap();
+        let restored: McTreeRoot<SimplePolicy> = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+        assert_eq!(restored.board(), tree.board());
+
+        let result = serde_json::to_string(&McResult::Draw).unwrap();
+        assert_eq!(
+            serde_json::from_str::<McResult>(&result).unwrap(),
+            McResult::Draw
+        );
     }
 }
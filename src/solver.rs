@@ -0,0 +1,282 @@
+use std::{
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bitboard::BitBoard,
+    dfpn::{read_board, read_u64, write_board, Dfpn},
+    proof::ProofTree,
+    search::Proof,
+    N,
+};
+
+/**
+ * チェックポイントのファイルの先頭
+ */
+const MAGIC: &[u8; 8] = b"YMSOLVE1";
+
+/**
+ * 一手目ごとのわかっていること。どちらも一手目を打った側から見る。
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MoveStatus {
+    pub hand: usize,
+    /// 勝ちきれるか
+    pub wins: Option<bool>,
+    /// 相手に勝ちきられるか
+    pub loses: Option<bool>,
+}
+
+impl MoveStatus {
+    pub fn proof(&self) -> Option<Proof> {
+        match (self.wins, self.loses) {
+            (Some(true), _) => Some(Proof::Win),
+            (_, Some(true)) => Some(Proof::Lose),
+            (Some(false), Some(false)) => Some(Proof::Draw),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * 局面の読み切りを少しずつ進める。何日もかかる読みのためのもので、
+ * 途中の表をsaveで書き出し、loadで読み込めば続きから調べられる。
+ * proveと同じく、まず手番側の勝ちを、勝てなければ相手の勝ちを調べる。
+ */
+#[derive(Clone, Debug)]
+pub struct Solver {
+    root: BitBoard,
+    /// 手番側の勝ちを調べる
+    first: Dfpn,
+    /// 相手の勝ちを調べる
+    second: Dfpn,
+    /// これまでにかかった時間。読み込んだ後も足していく
+    elapsed: Duration,
+}
+
+impl Solver {
+    pub fn new(root: &BitBoard) -> Self {
+        let player = root.next_player();
+        Solver {
+            root: root.clone(),
+            first: Dfpn::new(player),
+            second: Dfpn::new(player.next_player()),
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn root(&self) -> &BitBoard {
+        &self.root
+    }
+
+    pub fn nodes(&self) -> u64 {
+        self.first.nodes() + self.second.nodes()
+    }
+
+    pub fn table_len(&self) -> usize {
+        self.first.table_len() + self.second.table_len()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /**
+     * 手番側の勝ちを調べ終えて、相手の勝ちを調べているか
+     */
+    pub fn second_phase(&self) -> bool {
+        self.first.proven(&self.root) == Some(false)
+    }
+
+    /**
+     * 手番側から見た結果。まだわからなければNone
+     */
+    pub fn result(&self) -> Option<Proof> {
+        if self.root.is_won() {
+            return Some(Proof::Lose);
+        }
+        if self.root.is_full() {
+            return Some(Proof::Draw);
+        }
+        match self.first.proven(&self.root)? {
+            true => Some(Proof::Win),
+            false => match self.second.proven(&self.root)? {
+                true => Some(Proof::Lose),
+                false => Some(Proof::Draw),
+            },
+        }
+    }
+
+    /**
+     * 勝ちなら勝つ手、引き分けなら引き分けにできる手
+     */
+    pub fn best(&self) -> Option<usize> {
+        match self.result()? {
+            Proof::Win => self.first.proven_move(&self.root),
+            Proof::Draw => self.second.proven_move(&self.root),
+            Proof::Lose => None,
+        }
+    }
+
+    /**
+     * どちらかの勝ちとわかっていれば、その証明木
+     */
+    pub fn proof_tree(&mut self) -> Option<ProofTree> {
+        match self.result()? {
+            Proof::Win => self.first.proof_tree(&self.root),
+            Proof::Lose => self.second.proof_tree(&self.root),
+            Proof::Draw => None,
+        }
+    }
+
+    /**
+     * 最大max_nodesノードまで読みを進める。return 結果がわかればSome
+     */
+    pub fn step(&mut self, max_nodes: u64) -> Option<Proof> {
+        if self.result().is_some() {
+            return self.result();
+        }
+        let start = Instant::now();
+        if self.second_phase() {
+            self.second.search(&self.root, max_nodes);
+        } else if self.first.search(&self.root, max_nodes) == Some(false) {
+            // 勝てないとわかった一手目を覚えておけば足りる
+            self.first.shrink(0);
+        }
+        self.elapsed += start.elapsed();
+        self.result()
+    }
+
+    /**
+     * 表が合わせてmax_entriesに収まるよう、調べている方の表を減らす
+     */
+    pub fn shrink(&mut self, max_entries: usize) {
+        if self.second_phase() {
+            let rest = max_entries.saturating_sub(self.first.table_len());
+            self.second.shrink(rest);
+        } else {
+            self.first.shrink(max_entries);
+        }
+    }
+
+    /**
+     * 対称なものを除いた一手目ごとの途中結果
+     */
+    pub fn moves(&self) -> Vec<MoveStatus> {
+        let mut seen: Vec<BitBoard> = vec![];
+        let mut moves = vec![];
+        for hand in 0..N * N {
+            let Some(child) = self.root.put(hand) else {
+                continue;
+            };
+            let key = child.canonical();
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key);
+            let status = if child.is_won() {
+                MoveStatus {
+                    hand,
+                    wins: Some(true),
+                    loses: Some(false),
+                }
+            } else {
+                MoveStatus {
+                    hand,
+                    wins: self.first.proven(&child),
+                    loses: self.second.proven(&child),
+                }
+            };
+            moves.push(status);
+        }
+        moves
+    }
+
+    pub fn save(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        write_board(w, &self.root)?;
+        w.write_all(&(self.elapsed.as_millis() as u64).to_le_bytes())?;
+        self.first.save(w)?;
+        self.second.save(w)
+    }
+
+    pub fn load(r: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a solver checkpoint",
+            ));
+        }
+        let root = read_board(r)?;
+        let elapsed = Duration::from_millis(read_u64(r)?);
+        let first = Dfpn::load(r)?;
+        let second = Dfpn::load(r)?;
+        if first.attacker() != root.next_player() || second.attacker() == first.attacker() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "tables do not match the position",
+            ));
+        }
+        Ok(Solver {
+            root,
+            first,
+            second,
+            elapsed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfpn::{endgame, prove};
+
+    #[test]
+    fn test_solver() {
+        // 勝負のつかないまま空きが14になるまで打った、読み切りに手間のかかる局面
+        let (board, expected) = (0..)
+            .map(|seed| endgame(seed, 14))
+            .map(|board| {
+                let expected = prove(&board, u64::MAX);
+                (board, expected)
+            })
+            .find(|(_, expected)| expected.nodes > 1000)
+            .unwrap();
+
+        let mut solver = Solver::new(&board);
+        let mut steps = 0;
+        while solver.step(100).is_none() {
+            // 途中で書き出して読み込み直しても続けられる
+            let mut bytes = vec![];
+            solver.save(&mut bytes).unwrap();
+            solver = Solver::load(&mut bytes.as_slice()).unwrap();
+            solver.shrink(1000);
+            steps += 1;
+        }
+        assert!(steps > 0);
+        assert_eq!(solver.result(), expected.proof);
+        if let Some(best) = solver.best() {
+            let after = board.put(best).unwrap();
+            assert_eq!(
+                prove(&after, u64::MAX).proof.map(Proof::flip),
+                expected.proof
+            );
+        }
+
+        for status in solver.moves() {
+            if let Some(proof) = status.proof() {
+                let after = board.put(status.hand).unwrap();
+                assert_eq!(prove(&after, u64::MAX).proof.map(Proof::flip), Some(proof));
+            }
+        }
+
+        if let Some(tree) = solver.proof_tree() {
+            assert!(crate::proof::verify(&tree).is_ok());
+        }
+
+        assert!(Solver::load(&mut &b"YMSOLVE0"[..]).is_err());
+    }
+}